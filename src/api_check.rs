@@ -0,0 +1,53 @@
+use gmec::types::error_chain::{ErrorChain, ErrorPropogation};
+
+use crate::internal::*;
+
+pub(crate) const SEMVER_CHECKS_INSTALL: &str = "cargo install cargo-semver-checks --locked";
+
+pub(crate) fn semver_checks_installed() -> bool {
+    return get_cli_output("cargo", &["semver-checks", "--version"]).map(|output| output.status.success()).unwrap_or(false);
+}
+
+/// How big the public API changes between `baseline_tag` and the working tree are (`Major` meaning breaking,
+/// before applying the pre-1.0 rules), as reported by `cargo semver-checks`, with its summary line
+pub(crate) fn public_api_change(package: Option<&str>, baseline_tag: &str) -> Result<(VersionPart, String), ErrorChain> {
+    let mut args = vec!["semver-checks", "check-release", "--baseline-rev", baseline_tag, "--release-type", "patch"];
+    if let Some(package) = package {
+        args.extend(["--package", package]);
+    }
+    let output = get_cli_output("cargo", &args).on_error("could not run cargo semver-checks")?;
+    let report = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    return parse_semver_checks_report(output.status.success(), &report).do_on_error(|| {
+        let tail: Vec<&str> = report.lines().rev().take(10).collect::<Vec<&str>>().into_iter().rev().collect();
+        format!("cargo semver-checks failed against {}:\n{}", baseline_tag, tail.join("\n"))
+    });
+}
+
+/// Reads the outcome of `cargo semver-checks --release-type patch`: success means a patch is enough,
+/// otherwise its summary says whether a new major or minor version is needed
+fn parse_semver_checks_report(success: bool, report: &str) -> Option<(VersionPart, String)> {
+    if success {
+        return Some((VersionPart::Patch, String::from("no semver violations found")));
+    }
+    let summary = report.lines().map(|line| line.trim()).find(|line| line.contains("requires new major version") || line.contains("requires new minor version"))?;
+    let summary = summary.strip_prefix("Summary").unwrap_or(summary).trim().to_owned();
+    if summary.contains("requires new major version") {
+        return Some((VersionPart::Major, summary));
+    }
+    return Some((VersionPart::Minor, summary));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_semver_checks_report() {
+        let breaking = "     Parsing foo v1.2.3 (current)\n--- failure function_missing: pub fn removed or renamed ---\n     Summary semver requires new major version: 1 major and 0 minor checks failed\n";
+        assert_eq!(parse_semver_checks_report(false, breaking), Some((VersionPart::Major, String::from("semver requires new major version: 1 major and 0 minor checks failed"))));
+        let minor = "     Summary semver requires new minor version: 0 major and 1 minor checks failed\n";
+        assert_eq!(parse_semver_checks_report(false, minor).map(|(part, _)| part), Some(VersionPart::Minor));
+        assert_eq!(parse_semver_checks_report(true, "     Summary no semver update required\n").map(|(part, _)| part), Some(VersionPart::Patch));
+        assert_eq!(parse_semver_checks_report(false, "error: baseline rev not found\n"), None);
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::BTreeMap;
+
+use gmec::types::error_chain::ErrorChain;
+
+use crate::config::*;
+use crate::conventional::*;
+use crate::git::*;
+use crate::internal::*;
+use crate::report::*;
+use crate::workspace::*;
+
+pub(crate) const CHANGELOG_FILE: &str = "CHANGELOG.md";
+const UNRELEASED_HEADING: &str = "## [Unreleased]";
+const CHANGELOG_HEADER: &str = "# Changelog
+
+All notable changes to this project will be documented in this file.
+
+The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/),
+and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).
+";
+
+const CHANGELOG_SINCE: &str = "--since";
+
+/// The Keep a Changelog sections, in the order they are written
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(crate) enum ChangeKind {
+    Added,
+    Changed,
+    Deprecated,
+    Removed,
+    Fixed,
+    Security
+}
+
+impl ChangeKind {
+    /// Sorts a commit subject into a section, from its Conventional Commits type (`feat: ...`)
+    /// or otherwise its first word (`Fix ...`), and strips that prefix from the entry
+    fn classify(subject: &str) -> (ChangeKind, String) {
+        if let Some(commit) = ConventionalCommit::parse(subject) {
+            let kind = match commit.commit_type.to_lowercase().as_str() {
+                "feat" | "add" => ChangeKind::Added,
+                "fix" => ChangeKind::Fixed,
+                "security" | "sec" => ChangeKind::Security,
+                "deprecate" => ChangeKind::Deprecated,
+                "remove" => ChangeKind::Removed,
+                _ => ChangeKind::Changed
+            };
+            let entry = match commit.scope {
+                Some(scope) => format!("**{}**: {}", scope, commit.description),
+                None => commit.description.to_owned()
+            };
+            return (kind, entry);
+        }
+        let first_word = subject.split_whitespace().next().unwrap_or_default().to_lowercase();
+        let kind = if first_word.starts_with("add") {
+            ChangeKind::Added
+        } else if first_word.starts_with("fix") {
+            ChangeKind::Fixed
+        } else if first_word.starts_with("remove") || first_word.starts_with("delete") {
+            ChangeKind::Removed
+        } else if first_word.starts_with("deprecate") {
+            ChangeKind::Deprecated
+        } else if first_word.starts_with("security") {
+            ChangeKind::Security
+        } else {
+            ChangeKind::Changed
+        };
+        return (kind, subject.to_owned());
+    }
+
+    fn heading(&self) -> &'static str {
+        match self {
+            ChangeKind::Added => "### Added",
+            ChangeKind::Changed => "### Changed",
+            ChangeKind::Deprecated => "### Deprecated",
+            ChangeKind::Removed => "### Removed",
+            ChangeKind::Fixed => "### Fixed",
+            ChangeKind::Security => "### Security"
+        }
+    }
+}
+
+/// Changelog entries grouped by section
+pub(crate) struct ReleaseNotes {
+    entries: BTreeMap<ChangeKind, Vec<String>>
+}
+
+impl ReleaseNotes {
+    /// Commits with cargit's default message are left out, they never describe a change
+    pub fn from_subjects(subjects: &[String]) -> ReleaseNotes {
+        let mut entries: BTreeMap<ChangeKind, Vec<String>> = BTreeMap::new();
+        for subject in subjects.iter().map(|subject| subject.trim()).filter(|subject| !subject.is_empty() && *subject != DEFAULT_MSG) {
+            let (kind, entry) = ChangeKind::classify(subject);
+            let section = entries.entry(kind).or_default();
+            if !section.contains(&entry) {
+                section.push(entry);
+            }
+        }
+        return ReleaseNotes { entries };
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.entries.is_empty();
+    }
+
+    /// The sections of the notes, without a release heading
+    pub fn render(&self) -> String {
+        let mut sections = Vec::new();
+        for (kind, entries) in self.entries.iter() {
+            let mut section = format!("{}\n", kind.heading());
+            for entry in entries {
+                section.push_str(format!("\n- {}", entry).as_str());
+            }
+            sections.push(section);
+        }
+        return sections.join("\n\n");
+    }
+
+    /// The entries of each section, keyed by the section name in lowercase (eg. `"added"`)
+    pub fn to_json(&self) -> Json {
+        return Json::Object(self.entries.iter()
+            .map(|(kind, entries)| (kind.heading().trim_start_matches("### ").to_lowercase(), Json::strs(entries)))
+            .collect());
+    }
+}
+
+/// Notes for the commits since the previous version tag of `tag_format` (or since `since`) that
+/// touch `path`, oldest first, followed by `message` (the message of the commit about to be made)
+pub(crate) fn collect_release_notes(git: &dyn GitBackend, tag_format: &TagFormat, since: Option<&str>, path: &str, message: Option<&str>) -> Result<(Option<String>, ReleaseNotes), ErrorChain> {
+    let since = match since {
+        Some(since) => Some(since.to_owned()),
+        None => find_previous_version_tag(git, tag_format)?.map(|(tag, _)| tag)
+    };
+    let mut messages = git.commit_messages_since(since.as_deref(), path)?;
+    messages.reverse();
+    messages.extend(message.map(|message| message.to_owned()));
+    let subjects: Vec<String> = messages.iter().map(|message| message.lines().next().unwrap_or_default().to_owned()).collect();
+    return Ok((since, ReleaseNotes::from_subjects(&subjects)));
+}
+
+/// Adds a `## [<version>] - <date>` section to a changelog (a new one if `existing` is `None`).
+/// Hand written notes under `## [Unreleased]` are moved into the new section and take the place
+/// of the generated `notes`, otherwise the section goes right below the `Unreleased` heading
+pub(crate) fn insert_release_section(existing: Option<&str>, version: &Version, date: &str, notes: &ReleaseNotes) -> String {
+    let heading = format!("## [{}] - {}", version, date);
+    let section = |body: &str| -> String {
+        match body.trim() {
+            "" => format!("{}\n", heading),
+            body => format!("{}\n\n{}\n", heading, body)
+        }
+    };
+    let existing = match existing {
+        Some(existing) => existing,
+        None => return format!("{}\n{}\n\n{}", CHANGELOG_HEADER, UNRELEASED_HEADING, section(&notes.render()))
+    };
+    let lines: Vec<&str> = existing.lines().collect();
+    let is_release_heading = |line: &&str| line.starts_with("## ");
+    let unreleased = lines.iter().position(|line| line.trim().to_lowercase().starts_with(&UNRELEASED_HEADING.to_lowercase()));
+    let (before, body, after) = match unreleased {
+        Some(unreleased) => {
+            let next = lines[unreleased + 1..].iter().position(is_release_heading).map(|index| index + unreleased + 1).unwrap_or(lines.len());
+            (&lines[..=unreleased], lines[unreleased + 1..next].join("\n"), &lines[next..])
+        }
+        None => match lines.iter().position(is_release_heading) {
+            Some(first_release) => (&lines[..first_release], String::new(), &lines[first_release..]),
+            None => (&lines[..], String::new(), &lines[lines.len()..])
+        }
+    };
+    let body = if body.trim().is_empty() { notes.render() } else { body };
+    let mut changelog = before.join("\n").trim_end().to_owned();
+    if !changelog.is_empty() {
+        changelog.push_str("\n\n");
+    }
+    changelog.push_str(&section(&body));
+    if !after.is_empty() {
+        changelog.push('\n');
+        changelog.push_str(&after.join("\n"));
+        changelog.push('\n');
+    }
+    return changelog;
+}
+
+pub(crate) fn changelog_process<I>(mut args_iter: I) -> Result<Report, ErrorChain>
+where I: Iterator<Item = String> {
+    let git = open_git_backend()?;
+    let mut since: Option<String> = None;
+    while let Some(next_arg) = args_iter.next() {
+        match next_arg.as_str() {
+            CHANGELOG_SINCE => {
+                let tag = args_iter.next().ok_or_else(|| usage_error(format!("the '{}' option must be followed by a tag or commit", CHANGELOG_SINCE)))?;
+                if !git.ref_exists(&tag)? {
+                    return Err(usage_error(format!("'{}' is not a tag, branch or commit in this repository", tag)));
+                }
+                since = Some(tag);
+            }
+            _ if take_output_format(&next_arg, &mut args_iter)? => {}
+            _ => return Err(usage_error(format!("invalid argument passed to changelog mode: '{}'", next_arg)))
+        }
+    }
+    let config = load_config()?;
+    let tag_format = current_tag_format(config.tag_template.as_deref())?;
+    let (since, notes) = collect_release_notes(git.as_ref(), &tag_format, since.as_deref(), ".", None)?;
+    let since_message = match &since {
+        Some(since) => format!("Changes since {}:", since),
+        None => String::from("Changes since the first commit:")
+    };
+    let message = if notes.is_empty() {
+        format!("{}\n(none)", since_message)
+    } else {
+        format!("{}\n\n{}\n\n{}", since_message, UNRELEASED_HEADING, notes.render())
+    };
+    return Ok(Report::new(message)
+        .field("since", Json::opt_str(since.as_deref()))
+        .field("sections", notes.to_json()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notes(subjects: &[&str]) -> ReleaseNotes {
+        return ReleaseNotes::from_subjects(&subjects.iter().map(|s| s.to_string()).collect::<Vec<String>>());
+    }
+
+    #[test]
+    fn test_release_notes_sections() {
+        let notes = notes(&["feat(cli): add --json", "Fix crash on empty tag", "refactor!: drop old config", "update docs", DEFAULT_MSG, "update docs"]);
+        assert_eq!(notes.render(), "### Added\n\n- **cli**: add --json\n\n### Changed\n\n- drop old config\n- update docs\n\n### Fixed\n\n- Fix crash on empty tag");
+        assert!(ReleaseNotes::from_subjects(&[DEFAULT_MSG.to_owned()]).is_empty());
+    }
+
+    #[test]
+    fn test_insert_release_section() {
+        let version = Version::parse("1.1.0").unwrap();
+        let new_changelog = insert_release_section(None, &version, "2024-03-01", &notes(&["feat: one"]));
+        assert!(new_changelog.starts_with("# Changelog\n"));
+        assert!(new_changelog.ends_with("## [Unreleased]\n\n## [1.1.0] - 2024-03-01\n\n### Added\n\n- one\n"), "{}", new_changelog);
+
+        let existing = "# Changelog\n\n## [Unreleased]\n\n## [1.0.0] - 2024-01-01\n\n### Added\n\n- first\n";
+        let updated = insert_release_section(Some(existing), &version, "2024-03-01", &notes(&["fix: two"]));
+        assert_eq!(updated, "# Changelog\n\n## [Unreleased]\n\n## [1.1.0] - 2024-03-01\n\n### Fixed\n\n- two\n\n## [1.0.0] - 2024-01-01\n\n### Added\n\n- first\n");
+
+        let hand_written = "# Changelog\n\n## [Unreleased]\n\n### Removed\n\n- the old API\n\n## [1.0.0] - 2024-01-01\n";
+        let updated = insert_release_section(Some(hand_written), &version, "2024-03-01", &notes(&["fix: two"]));
+        assert_eq!(updated, "# Changelog\n\n## [Unreleased]\n\n## [1.1.0] - 2024-03-01\n\n### Removed\n\n- the old API\n\n## [1.0.0] - 2024-01-01\n");
+    }
+}
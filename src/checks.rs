@@ -0,0 +1,89 @@
+use std::fmt::{self, Display};
+
+use gmec::types::error_chain::ErrorChain;
+
+use crate::internal::*;
+use crate::report::*;
+
+const CHECK_FMT: &str = "fmt";
+const CHECK_CLIPPY: &str = "clippy";
+const CHECK_TEST: &str = "test";
+const CHECK_DOC: &str = "doc";
+
+/// A command that must succeed before save changes anything. The builtin names `fmt`, `clippy`,
+/// `test` and `doc` expand to the matching cargo command, anything else is run as a command line
+#[derive(Debug, PartialEq)]
+pub(crate) struct Check {
+    pub name: String,
+    pub command: CliCommand
+}
+
+impl Check {
+    pub fn parse(spec: &str) -> Result<Check, ErrorChain> {
+        let command = match spec.trim() {
+            CHECK_FMT => CliCommand::new("cargo", &["fmt", "--all", "--", "--check"]),
+            CHECK_CLIPPY => CliCommand::new("cargo", &["clippy", "--workspace", "--all-targets", "--", "-D", "warnings"]),
+            CHECK_TEST => CliCommand::new("cargo", &["test", "--workspace"]),
+            CHECK_DOC => CliCommand::new("cargo", &["doc", "--workspace", "--no-deps"]),
+            command_line => CliCommand::parse(command_line)?
+        };
+        return Ok(Check { name: spec.trim().to_owned(), command });
+    }
+}
+
+impl Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.name == self.command.to_string() {
+            return write!(f, "{}", self.command);
+        }
+        return write!(f, "{} ({})", self.name, self.command);
+    }
+}
+
+/// Runs every check, even after one fails, so the summary lists all of the failures at once
+pub(crate) fn run_checks(checks: &[Check]) -> Result<(), ErrorChain> {
+    let mut failed = Vec::new();
+    for (index, check) in checks.iter().enumerate() {
+        print_message(&format!("Running check {}/{}: {}\n", index + 1, checks.len(), check));
+        if let Err(err) = check.command.run() {
+            failed.push(format!("{}: {}", check, err));
+        }
+    }
+    if failed.is_empty() {
+        return Ok(());
+    }
+    let mut summary = format!("{} of {} pre-save checks failed, nothing was changed:", failed.len(), checks.len());
+    for failure in failed {
+        summary.push_str(format!("\n  - {}", failure).as_str());
+    }
+    return Err(ErrorChain::new(summary));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_parse() {
+        let clippy = Check::parse("clippy").unwrap();
+        assert_eq!(clippy.command, CliCommand::new("cargo", &["clippy", "--workspace", "--all-targets", "--", "-D", "warnings"]));
+        assert_eq!(clippy.to_string(), "clippy (cargo clippy --workspace --all-targets -- -D warnings)");
+        let custom = Check::parse("cargo deny check").unwrap();
+        assert_eq!(custom.command, CliCommand::new("cargo", &["deny", "check"]));
+        assert_eq!(custom.to_string(), "cargo deny check");
+        assert!(Check::parse("").is_err());
+    }
+
+    #[test]
+    fn test_run_checks_reports_every_failure() {
+        let checks = vec![
+            Check::parse("git --cargit-invalid-option").unwrap(),
+            Check::parse("git --version").unwrap(),
+            Check::parse("git --cargit-other-invalid-option").unwrap(),
+        ];
+        let err = run_checks(&checks).unwrap_err().to_string();
+        assert!(err.starts_with("2 of 3 pre-save checks failed"), "{}", err);
+        assert!(err.contains("--cargit-invalid-option") && err.contains("--cargit-other-invalid-option"), "{}", err);
+        assert!(run_checks(&checks[1..2]).is_ok());
+    }
+}
@@ -0,0 +1,216 @@
+use std::{env, fs, path::{Path, PathBuf}};
+
+use gmec::types::error_chain::{ErrorChain, ErrorPropogation};
+use toml_edit::{DocumentMut, Item, TableLike};
+
+use crate::checks::*;
+use crate::internal::*;
+use crate::manifest::*;
+
+pub(crate) const PROJECT_CONFIG_FILE: &str = ".cargit.toml";
+const USER_CONFIG_DIR: &str = "cargit";
+const USER_CONFIG_FILE: &str = "config.toml";
+const METADATA_TOOL: &str = "cargit";
+
+const KEY_BUMP: &str = "bump";
+const KEY_MESSAGE: &str = "message";
+const KEY_TAG_TEMPLATE: &str = "tag-template";
+const KEY_REMOTE: &str = "remote";
+const KEY_BRANCH: &str = "branch";
+const KEY_PUBLISH: &str = "publish";
+const KEY_ROLLBACK: &str = "rollback";
+const KEY_ANNOTATE: &str = "annotate";
+const KEY_SIGN: &str = "sign";
+const KEY_CHECKS: &str = "checks";
+const KEY_CHANGELOG: &str = "changelog";
+const KEY_SEMVER_CHECK: &str = "semver-check";
+const KEY_MAIN_BRANCH: &str = "main-branch";
+const KEY_MERGE_STRATEGY: &str = "merge-strategy";
+
+/// Defaults for cargit commands. Every field is optional so that layers can be merged, from lowest
+/// to highest priority: the user config, `[workspace.metadata.cargit]`, the workspace `.cargit.toml`,
+/// `[package.metadata.cargit]` and the crate `.cargit.toml`. CLI flags override all of them
+#[derive(Default)]
+pub(crate) struct CargitConfig {
+    pub bump: Option<VersionPart>,
+    pub message: Option<String>,
+    pub tag_template: Option<String>,
+    pub remote: Option<String>,
+    pub branch: Option<String>,
+    pub publish: Option<bool>,
+    pub rollback: Option<ResetMode>,
+    pub annotate: Option<bool>,
+    pub sign: Option<bool>,
+    pub checks: Option<Vec<Check>>,
+    pub changelog: Option<bool>,
+    pub semver_check: Option<bool>,
+    /// The branch start creates feature branches from and finish merges them into
+    pub main_branch: Option<String>,
+    pub merge_strategy: Option<MergeStrategy>
+}
+
+impl CargitConfig {
+    fn from_table(table: &dyn TableLike, source: &str) -> Result<CargitConfig, ErrorChain> {
+        let mut config = CargitConfig::default();
+        for (key, item) in table.iter() {
+            match key {
+                KEY_BUMP => {
+                    let bump = config_str(item, key, source)?;
+                    config.bump = Some(VersionPart::parse(&bump).do_on_error(|| format!("invalid '{}' in {}: '{}', must be 'major', 'minor' or 'patch'", key, source, bump))?);
+                }
+                KEY_MESSAGE => config.message = Some(config_str(item, key, source)?),
+                KEY_TAG_TEMPLATE => {
+                    let template = config_str(item, key, source)?;
+                    if !template.contains("{version}") {
+                        return Err(ErrorChain::new(format!("invalid '{}' in {}: '{}' must contain '{{version}}'", key, source, template)));
+                    }
+                    config.tag_template = Some(template);
+                }
+                KEY_REMOTE => config.remote = Some(config_str(item, key, source)?),
+                KEY_BRANCH => config.branch = Some(config_str(item, key, source)?),
+                KEY_PUBLISH => config.publish = Some(config_bool(item, key, source)?),
+                KEY_ANNOTATE => config.annotate = Some(config_bool(item, key, source)?),
+                KEY_SIGN => config.sign = Some(config_bool(item, key, source)?),
+                KEY_CHANGELOG => config.changelog = Some(config_bool(item, key, source)?),
+                KEY_SEMVER_CHECK => config.semver_check = Some(config_bool(item, key, source)?),
+                KEY_MAIN_BRANCH => config.main_branch = Some(config_str(item, key, source)?),
+                KEY_MERGE_STRATEGY => {
+                    let strategy = config_str(item, key, source)?;
+                    config.merge_strategy = Some(MergeStrategy::parse(&strategy).do_on_error(|| format!("invalid '{}' in {}: '{}', must be 'merge', 'squash' or 'rebase'", key, source, strategy))?);
+                }
+                KEY_CHECKS => {
+                    let checks = item.as_array().do_on_error(|| format!("'{}' in {} must be a list of check names or commands", key, source))?;
+                    let mut parsed = Vec::new();
+                    for check in checks.iter() {
+                        let spec = check.as_str().do_on_error(|| format!("'{}' in {} must only contain strings", key, source))?;
+                        parsed.push(Check::parse(spec).do_on_error(|| format!("invalid check '{}' in {}", spec, source))?);
+                    }
+                    config.checks = Some(parsed);
+                }
+                KEY_ROLLBACK => {
+                    let rollback = config_str(item, key, source)?;
+                    config.rollback = Some(ResetMode::parse(&rollback).do_on_error(|| format!("invalid '{}' in {}: '{}', must be 'soft' or 'hard'", key, source, rollback))?);
+                }
+                _ => return Err(ErrorChain::new(format!("unknown key '{}' in {}", key, source)))
+            }
+        }
+        return Ok(config);
+    }
+
+    fn from_file(path: &Path) -> Result<Option<CargitConfig>, ErrorChain> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let config_str = fs::read_to_string(path).on_error(format!("{} could not be parsed to String", path.display()))?;
+        let document = config_str.parse::<DocumentMut>().on_error(format!("{} is not a valid TOML document", path.display()))?;
+        return Ok(Some(CargitConfig::from_table(document.as_table(), &path.display().to_string())?));
+    }
+
+    /// Overrides the values of `self` with those set in `higher`
+    fn merge(&mut self, higher: CargitConfig) {
+        self.bump = higher.bump.or(self.bump);
+        self.message = higher.message.or(self.message.take());
+        self.tag_template = higher.tag_template.or(self.tag_template.take());
+        self.remote = higher.remote.or(self.remote.take());
+        self.branch = higher.branch.or(self.branch.take());
+        self.publish = higher.publish.or(self.publish);
+        self.rollback = higher.rollback.or(self.rollback);
+        self.annotate = higher.annotate.or(self.annotate);
+        self.sign = higher.sign.or(self.sign);
+        self.checks = higher.checks.or(self.checks.take());
+        self.changelog = higher.changelog.or(self.changelog);
+        self.semver_check = higher.semver_check.or(self.semver_check);
+        self.main_branch = higher.main_branch.or(self.main_branch.take());
+        self.merge_strategy = higher.merge_strategy.or(self.merge_strategy);
+    }
+
+    fn merge_project_dir(&mut self, dir: &Path) -> Result<(), ErrorChain> {
+        let manifest_path = dir.join(CARGO_MANIFEST);
+        if manifest_path.is_file() {
+            let manifest = Manifest::load(&manifest_path)?;
+            for (table_name, table) in manifest.tool_metadata(METADATA_TOOL) {
+                let source = format!("[{}.{}] of {}", table_name, METADATA_TOOL, manifest_path.display());
+                self.merge(CargitConfig::from_table(table, &source)?);
+            }
+        }
+        if let Some(file_config) = CargitConfig::from_file(&dir.join(PROJECT_CONFIG_FILE))? {
+            self.merge(file_config);
+        }
+        return Ok(());
+    }
+}
+
+fn config_str(item: &Item, key: &str, source: &str) -> Result<String, ErrorChain> {
+    return item.as_str().map(|s| s.to_owned()).do_on_error(|| format!("'{}' in {} must be a string", key, source));
+}
+
+fn config_bool(item: &Item, key: &str, source: &str) -> Result<bool, ErrorChain> {
+    return item.as_bool().do_on_error(|| format!("'{}' in {} must be true or false", key, source));
+}
+
+/// `$XDG_CONFIG_HOME/cargit/config.toml`, falling back to `~/.config/cargit/config.toml` (`%APPDATA%\cargit\config.toml` on Windows)
+pub(crate) fn user_config_path() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    return Some(config_dir.join(USER_CONFIG_DIR).join(USER_CONFIG_FILE));
+}
+
+/// Loads and merges every config layer that applies to the crate in the current directory
+pub(crate) fn load_config() -> Result<CargitConfig, ErrorChain> {
+    let mut config = CargitConfig::default();
+    if let Some(user_config_path) = user_config_path() {
+        if let Some(user_config) = CargitConfig::from_file(&user_config_path)? {
+            config.merge(user_config);
+        }
+    }
+    let crate_dir = env::current_dir().on_error("could not get the current directory")?;
+    if crate_dir.join(CARGO_MANIFEST).is_file() {
+        if let Some(root_manifest_path) = find_workspace_root_manifest(Path::new(CARGO_MANIFEST))? {
+            let root_dir = root_manifest_path.parent().on_error("workspace root manifest has no parent directory")?;
+            if fs::canonicalize(&crate_dir).ok().as_deref() != Some(root_dir) {
+                config.merge_project_dir(root_dir)?;
+            }
+        }
+    }
+    config.merge_project_dir(&crate_dir)?;
+    return Ok(config);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(config_str: &str) -> Result<CargitConfig, ErrorChain> {
+        let document = config_str.parse::<DocumentMut>().unwrap();
+        return CargitConfig::from_table(document.as_table(), PROJECT_CONFIG_FILE);
+    }
+
+    #[test]
+    fn test_config_layers_merge() {
+        let mut merged = config("bump = \"minor\"\nmessage = \"release\"\npublish = true\n").unwrap();
+        merged.merge(config("bump = \"major\"\ntag-template = \"v{version}\"\nrollback = \"hard\"\n").unwrap());
+        assert_eq!(merged.bump, Some(VersionPart::Major));
+        assert_eq!(merged.message.as_deref(), Some("release"));
+        assert_eq!(merged.tag_template.as_deref(), Some("v{version}"));
+        assert_eq!(merged.publish, Some(true));
+        assert_eq!(merged.rollback, Some(ResetMode::Hard));
+        merged.merge(config("main-branch = \"trunk\"\nmerge-strategy = \"squash\"\n").unwrap());
+        assert_eq!(merged.main_branch.as_deref(), Some("trunk"));
+        assert_eq!(merged.merge_strategy, Some(MergeStrategy::Squash));
+        merged.merge(config("checks = [\"fmt\", \"cargo deny check\"]\n").unwrap());
+        let checks: Vec<String> = merged.checks.unwrap().iter().map(|check| check.name.clone()).collect();
+        assert_eq!(checks, vec!["fmt", "cargo deny check"]);
+    }
+
+    #[test]
+    fn test_invalid_config_is_an_error() {
+        assert!(config("bump = \"huge\"\n").is_err());
+        assert!(config("publish = \"yes\"\n").is_err());
+        assert!(config("tag-template = \"release\"\n").is_err());
+        assert!(config("unknown = 1\n").is_err());
+        assert!(config("checks = \"fmt\"\n").is_err());
+        assert!(config("merge-strategy = \"octopus\"\n").is_err());
+        assert!(config("checks = [\"echo 'oops\"]\n").is_err());
+    }
+}
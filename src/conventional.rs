@@ -0,0 +1,95 @@
+use crate::internal::*;
+
+/// A commit message following Conventional Commits (`<type>[(<scope>)][!]: <description>`)
+#[derive(PartialEq, Debug)]
+pub(crate) struct ConventionalCommit<'a> {
+    pub commit_type: &'a str,
+    pub scope: Option<&'a str>,
+    pub breaking: bool,
+    pub description: &'a str
+}
+
+impl<'a> ConventionalCommit<'a> {
+    /// Parses the subject line, and the footers of the body for a `BREAKING CHANGE:`
+    pub fn parse(message: &'a str) -> Option<ConventionalCommit<'a>> {
+        let subject = message.lines().next()?.trim();
+        let (prefix, description) = subject.split_once(':')?;
+        let (prefix, bang) = match prefix.strip_suffix('!') {
+            Some(prefix) => (prefix, true),
+            None => (prefix, false)
+        };
+        let (commit_type, scope) = match prefix.split_once('(') {
+            Some((commit_type, scope)) => (commit_type, Some(scope.strip_suffix(')')?)),
+            None => (prefix, None)
+        };
+        if commit_type.is_empty() || !commit_type.chars().all(|c| c.is_ascii_alphabetic()) {
+            return None;
+        }
+        let breaking_footer = message.lines().skip(1).any(|line| line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:"));
+        return Some(ConventionalCommit { commit_type, scope, breaking: bang || breaking_footer, description: description.trim() });
+    }
+
+    pub fn is_type(&self, commit_type: &str) -> bool {
+        return self.commit_type.eq_ignore_ascii_case(commit_type);
+    }
+}
+
+/// Picks the version part to bump from commit messages: a breaking change bumps major (minor before 1.0.0),
+/// a `feat` bumps minor and anything else bumps patch. Returns the part and a sentence explaining the choice
+pub(crate) fn choose_bump_part(messages: &[String], current: &Version) -> (VersionPart, String) {
+    let commits: Vec<(&str, ConventionalCommit)> = messages.iter()
+        .filter_map(|message| ConventionalCommit::parse(message).map(|commit| (message.lines().next().unwrap_or_default().trim(), commit)))
+        .collect();
+    let count = |n: usize, singular: &str, plural: &str| -> String {
+        if n == 1 { format!("1 {}", singular) } else { format!("{} {}", n, plural) }
+    };
+    let breaking: Vec<&str> = commits.iter().filter(|(_, commit)| commit.breaking).map(|(subject, _)| *subject).collect();
+    if let Some(first) = breaking.first() {
+        let changes = count(breaking.len(), "breaking change", "breaking changes");
+        if current.major == 0 {
+            return (VersionPart::Minor, format!("minor, {} before 1.0.0, eg. '{}'", changes, first));
+        }
+        return (VersionPart::Major, format!("major, {}, eg. '{}'", changes, first));
+    }
+    let features: Vec<&str> = commits.iter().filter(|(_, commit)| commit.is_type("feat")).map(|(subject, _)| *subject).collect();
+    if let Some(first) = features.first() {
+        return (VersionPart::Minor, format!("minor, {}, eg. '{}'", count(features.len(), "new feature", "new features"), first));
+    }
+    let fixes: Vec<&str> = commits.iter().filter(|(_, commit)| commit.is_type("fix")).map(|(subject, _)| *subject).collect();
+    if let Some(first) = fixes.first() {
+        return (VersionPart::Patch, format!("patch, {}, eg. '{}'", count(fixes.len(), "fix", "fixes"), first));
+    }
+    return (VersionPart::Patch, format!("patch, no breaking change, feature or fix among {}", count(messages.len(), "commit", "commits")));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(messages: &[&str]) -> Vec<String> {
+        return messages.iter().map(|message| message.to_string()).collect();
+    }
+
+    #[test]
+    fn test_conventional_commit_parse() {
+        let commit = ConventionalCommit::parse("feat(cli)!: add --json\n\nbody").unwrap();
+        assert_eq!(commit, ConventionalCommit { commit_type: "feat", scope: Some("cli"), breaking: true, description: "add --json" });
+        assert!(ConventionalCommit::parse("fix: typo\n\nBREAKING CHANGE: renamed the flag").unwrap().breaking);
+        assert!(!ConventionalCommit::parse("fix: typo\n\nmentions BREAKING CHANGE: inline").unwrap().breaking);
+        assert_eq!(ConventionalCommit::parse("Update README"), None);
+        assert_eq!(ConventionalCommit::parse("see http://example.com: later"), None);
+    }
+
+    #[test]
+    fn test_choose_bump_part() {
+        let stable = Version::parse("1.4.2").unwrap();
+        let unstable = Version::parse("0.4.2").unwrap();
+        let changes = messages(&["fix: a", "feat(api): b", "docs: c"]);
+        assert_eq!(choose_bump_part(&changes, &stable), (VersionPart::Minor, String::from("minor, 1 new feature, eg. 'feat(api): b'")));
+        let breaking = messages(&["fix: a", "refactor!: drop b"]);
+        assert_eq!(choose_bump_part(&breaking, &stable).0, VersionPart::Major);
+        assert_eq!(choose_bump_part(&breaking, &unstable), (VersionPart::Minor, String::from("minor, 1 breaking change before 1.0.0, eg. 'refactor!: drop b'")));
+        assert_eq!(choose_bump_part(&messages(&["fix: a", "fix: b"]), &stable).1, "patch, 2 fixes, eg. 'fix: a'");
+        assert_eq!(choose_bump_part(&messages(&["Update README"]), &stable).0, VersionPart::Patch);
+    }
+}
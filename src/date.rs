@@ -0,0 +1,156 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gmec::types::error_chain::{ErrorChain, ErrorPropogation};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Seconds since the Unix epoch
+pub(crate) fn now() -> i64 {
+    return SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or_default() as i64;
+}
+
+/// Today's UTC date as `YYYY-MM-DD`
+pub(crate) fn today() -> String {
+    return format_date(now());
+}
+
+/// The UTC day of a Unix timestamp as `YYYY-MM-DD`
+pub(crate) fn format_date(timestamp: i64) -> String {
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(SECONDS_PER_DAY));
+    return format!("{:04}-{:02}-{:02}", year, month, day);
+}
+
+/// A Unix timestamp as `YYYY-MM-DD HH:MM:SS UTC`
+pub(crate) fn format_timestamp(timestamp: i64) -> String {
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(SECONDS_PER_DAY));
+    let seconds = timestamp.rem_euclid(SECONDS_PER_DAY);
+    return format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", year, month, day, seconds / 3_600, seconds % 3_600 / 60, seconds % 60);
+}
+
+/// Parses a UTC `YYYY-MM-DD`, `YYYY-MM-DD HH:MM` or `YYYY-MM-DD[T ]HH:MM:SS[Z]` into a Unix timestamp.
+/// A date alone means the end of that day, so every commit made on it is included
+pub(crate) fn parse_date_time(date_time: &str) -> Result<i64, ErrorChain> {
+    let invalid = || format!("invalid date '{}', expected YYYY-MM-DD, optionally followed by HH:MM[:SS] (UTC)", date_time);
+    let date_time = date_time.trim();
+    let date_time = date_time.strip_suffix('Z').unwrap_or(date_time);
+    let (date, time) = match date_time.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time.trim())),
+        None => (date_time, None)
+    };
+    let date: Vec<&str> = date.split('-').collect();
+    if date.len() != 3 {
+        return Err(ErrorChain::new(invalid()));
+    }
+    let year: i64 = date[0].parse().do_on_error(invalid)?;
+    let month: u32 = date[1].parse().do_on_error(invalid)?;
+    let day: u32 = date[2].parse().do_on_error(invalid)?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return Err(ErrorChain::new(invalid()));
+    }
+    let seconds = match time {
+        None => SECONDS_PER_DAY - 1,
+        Some(time) => {
+            let time: Vec<&str> = time.split(':').collect();
+            if time.len() < 2 || time.len() > 3 {
+                return Err(ErrorChain::new(invalid()));
+            }
+            let hours: i64 = time[0].parse().do_on_error(invalid)?;
+            let minutes: i64 = time[1].parse().do_on_error(invalid)?;
+            let seconds: i64 = match time.get(2) {
+                Some(seconds) => seconds.parse().do_on_error(invalid)?,
+                None => 0
+            };
+            if !(0..24).contains(&hours) || !(0..60).contains(&minutes) || !(0..60).contains(&seconds) {
+                return Err(ErrorChain::new(invalid()));
+            }
+            hours * 3_600 + minutes * 60 + seconds
+        }
+    };
+    return Ok(days_from_civil(year, month, day) * SECONDS_PER_DAY + seconds);
+}
+
+/// Parses a duration such as `2w`, `3 days` or `12h` into seconds. Months count as 30 days and years as 365
+pub(crate) fn parse_duration(duration: &str) -> Result<i64, ErrorChain> {
+    let invalid = || format!("invalid duration '{}', expected a number and a unit (s, m, h, d, w, mo or y), eg. '2w'", duration);
+    let duration = duration.trim();
+    let unit_start = duration.find(|c: char| !c.is_ascii_digit()).do_on_error(invalid)?;
+    let amount: i64 = duration[..unit_start].parse().do_on_error(invalid)?;
+    let unit_seconds = match duration[unit_start..].trim().to_lowercase().as_str() {
+        "s" | "sec" | "secs" | "second" | "seconds" => 1,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60,
+        "h" | "hour" | "hours" => 3_600,
+        "d" | "day" | "days" => SECONDS_PER_DAY,
+        "w" | "week" | "weeks" => 7 * SECONDS_PER_DAY,
+        "mo" | "month" | "months" => 30 * SECONDS_PER_DAY,
+        "y" | "year" | "years" => 365 * SECONDS_PER_DAY,
+        _ => return Err(ErrorChain::new(invalid()))
+    };
+    return amount.checked_mul(unit_seconds).do_on_error(invalid);
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+/// Converts days since 1970-01-01 to a proleptic Gregorian (year, month, day)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    return (year, month, day);
+}
+
+/// Converts a proleptic Gregorian date to days since 1970-01-01, the inverse of [`civil_from_days`]
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_index = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let day_of_year = (153 * month_index + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    return era * 146_097 + day_of_era - 719_468;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        for days in [-800_000, -1, 0, 59, 19_782, 20_513, 2_000_000] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn test_parse_date_time_and_duration() {
+        assert_eq!(parse_date_time("2024-02-29").unwrap(), 19_782 * 86_400 + 86_399);
+        assert_eq!(parse_date_time("2024-02-29 13:05").unwrap(), 19_782 * 86_400 + 47_100);
+        assert_eq!(parse_date_time("2024-02-29T13:05:09Z").unwrap(), 19_782 * 86_400 + 47_109);
+        assert_eq!(format_timestamp(19_782 * 86_400 + 47_109), "2024-02-29 13:05:09 UTC");
+        assert!(parse_date_time("2023-02-29").is_err());
+        assert!(parse_date_time("2024-02-29 25:00").is_err());
+        assert!(parse_date_time("yesterday").is_err());
+        assert_eq!(parse_duration("2w").unwrap(), 14 * 86_400);
+        assert_eq!(parse_duration("3 days").unwrap(), 3 * 86_400);
+        assert_eq!(parse_duration("90m").unwrap(), 5_400);
+        assert!(parse_duration("2").is_err());
+        assert!(parse_duration("w").is_err());
+        assert!(parse_duration("2 fortnights").is_err());
+    }
+}
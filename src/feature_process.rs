@@ -0,0 +1,294 @@
+use gmec::types::error_chain::{ErrorChain, ErrorPropogation};
+
+use crate::checks::*;
+use crate::config::*;
+use crate::git::*;
+use crate::internal::*;
+use crate::plan::*;
+use crate::report::*;
+use crate::save_process::save_process;
+
+const FEATURE_FROM: &str = "--from";
+const FEATURE_INTO: &str = "--into";
+const FEATURE_DRY_RUN: &str = "--dry-run";
+const FEATURE_MERGE: &str = "--merge";
+const FEATURE_SQUASH: &str = "--squash";
+const FEATURE_REBASE: &str = "--rebase";
+const FEATURE_WITH_MSG: &str = "-m";
+const FEATURE_KEEP_BRANCH: &str = "--keep-branch";
+const FEATURE_SKIP_CHECKS: &str = "--skip-checks";
+const FEATURE_SAVE: &str = "--save";
+
+/// Branches tried, in order, as the main branch when `main-branch` is not configured
+const DEFAULT_MAIN_BRANCHES: [&str; 2] = ["main", "master"];
+
+struct FinishModeOptions {
+    feature: Option<String>,
+    into: Option<String>,
+    strategy: Option<MergeStrategy>,
+    /// Message of the squash commit, git's list of the squashed commits when not given
+    commit_message: Option<String>,
+    keep_branch: bool,
+    skip_checks: bool,
+    dry_run: bool,
+    /// The arguments given after `--save`, `None` without it
+    save_args: Option<Vec<String>>
+}
+
+/// `cargit start <feature>`: creates a branch from the main branch and switches to it
+pub(crate) fn start_process<I>(args_iter: I) -> Result<Report, ErrorChain>
+where I: Iterator<Item = String> {
+    let mut feature = None;
+    let mut from = None;
+    let mut dry_run = false;
+    let mut args_iter = args_iter.peekable();
+    while let Some(next_arg) = args_iter.next() {
+        match next_arg.as_str() {
+            FEATURE_DRY_RUN => dry_run = true,
+            FEATURE_FROM => from = Some(args_iter.next().ok_or_else(|| usage_error(format!("the '{}' option must be followed by a branch name", FEATURE_FROM)))?),
+            _ if take_output_format(&next_arg, &mut args_iter)? => {}
+            _ if next_arg.starts_with('-') || feature.is_some() => {
+                return Err(usage_error(format!("invalid argument passed to start mode: '{}'", next_arg)));
+            }
+            _ => feature = Some(next_arg)
+        }
+    }
+    let feature = feature.ok_or_else(|| usage_error("start needs the name of the new branch, eg. 'cargit start add-login'"))?;
+    let git = open_git_backend()?;
+    let git = git.as_ref();
+    let from = match from {
+        Some(from) => from,
+        None => main_branch(git, load_config()?.main_branch)?
+    };
+    if !git.ref_exists(&from)? {
+        return Err(usage_error(format!("cannot start from '{}', it is not a branch, tag or commit", from)));
+    }
+    if git.ref_exists(&format!("refs/heads/{}", feature))? {
+        return Err(usage_error(format!("the branch '{}' already exists, 'git checkout {}' switches to it", feature, feature)));
+    }
+    let current_branch = git.branch_name().on_error("could not get branch name")?;
+    if current_branch != from && git.has_uncommitted_changes().on_error("could not check the working tree for uncommitted changes")? {
+        return Err(aborted(format!("Start aborted, the working tree has uncommitted changes on {}. Commit or stash them first (changes made on {} are carried over to the new branch)", current_branch, from)));
+    }
+    let mut plan = Plan::transactional();
+    plan.run_with_undo(git_branch_at(&feature, &from), Undo::Run(git_delete_branch(&feature, true)));
+    plan.run(git_checkout(&feature));
+    let report = Report::new(String::new())
+        .field("dry_run", Json::Bool(dry_run))
+        .field("branch", Json::str(&feature))
+        .field("from", Json::str(&from));
+    if dry_run {
+        let report = report.field("steps_planned", Json::strs(plan.summaries()));
+        return Ok(report.with_message(format!("Dry run, nothing was changed! Would have created {} from {} and switched to it\n{}", feature, from, plan)));
+    }
+    plan.execute().on_error(format!("Could not start {}", feature))?;
+    let report = report
+        .field("commit", Json::str(git.current_commit()?))
+        .field("steps_done", Json::strs(plan.summaries()));
+    return Ok(report.with_message(format!("Created {} from {} and switched to it, 'cargit finish' merges it back", feature, from)));
+}
+
+/// `cargit finish [<feature>]`: runs the checks, merges the feature branch into the main branch, deletes it
+/// and optionally saves a new version from there
+pub(crate) fn finish_process<I>(args_iter: I) -> Result<Report, ErrorChain>
+where I: Iterator<Item = String> {
+    let options = process_finish_args(args_iter).inspect_err(|_| record_failure(FailureKind::Usage))?;
+    let config = load_config()?;
+    let git = open_git_backend()?;
+    let git = git.as_ref();
+    let strategy = options.strategy.or(config.merge_strategy).unwrap_or(MergeStrategy::Merge);
+    if options.commit_message.is_some() && strategy != MergeStrategy::Squash {
+        return Err(usage_error(format!("'{}' sets the message of the squash commit, it can only be used with '{}' (or merge-strategy = \"squash\")", FEATURE_WITH_MSG, FEATURE_SQUASH)));
+    }
+    let into = match options.into {
+        Some(into) => into,
+        None => main_branch(git, config.main_branch)?
+    };
+    let feature = match options.feature {
+        Some(feature) => feature,
+        None if git.is_detatched_mode().on_error("could not verify head attatchement")? => {
+            return Err(aborted("Finish aborted, HEAD is detatched. Check out the feature branch, or name it: 'cargit finish <branch>'"));
+        }
+        None => git.branch_name().on_error("could not get branch name")?
+    };
+    if feature == into {
+        return Err(usage_error(format!("{} is the branch features are merged into, check out the feature branch or name it: 'cargit finish <branch>'", into)));
+    }
+    for branch in [&feature, &into] {
+        if !git.ref_exists(&format!("refs/heads/{}", branch))? {
+            return Err(usage_error(format!("there is no local branch named '{}'", branch)));
+        }
+    }
+    if git.has_uncommitted_changes().on_error("could not check the working tree for uncommitted changes")? {
+        return Err(aborted(format!("Finish aborted, the working tree has uncommitted changes. Commit them to {} first", feature)));
+    }
+
+    let checks = if options.skip_checks { Vec::new() } else { config.checks.unwrap_or_default() };
+    let mut plan = Plan::new();
+    match strategy {
+        MergeStrategy::Merge => {
+            plan.run(git_checkout(&into));
+            plan.run(git_merge(&feature));
+        }
+        MergeStrategy::Squash => {
+            plan.run(git_checkout(&into));
+            plan.run(git_merge_squash(&feature));
+            plan.run(match &options.commit_message {
+                Some(message) => git_commit_with_message(message),
+                None => git_commit_prepared_message()
+            });
+        }
+        MergeStrategy::Rebase => {
+            plan.run(git_rebase(&into, &feature));
+            plan.run(git_checkout(&into));
+            plan.run(git_merge_fast_forward(&feature));
+        }
+    }
+    if !options.keep_branch {
+        plan.run(git_delete_branch(&feature, strategy == MergeStrategy::Squash));
+    }
+    let done = match options.keep_branch {
+        true => format!("merged {} into {} ({}), {} was kept", feature, into, strategy.name(), feature),
+        false => format!("merged {} into {} ({}) and deleted {}", feature, into, strategy.name(), feature)
+    };
+    let report = Report::new(String::new())
+        .field("dry_run", Json::Bool(options.dry_run))
+        .field("branch", Json::str(&feature))
+        .field("into", Json::str(&into))
+        .field("strategy", Json::str(strategy.name()))
+        .field("checks", Json::strs(checks.iter().map(|check| &check.name)))
+        .field("deleted", Json::Bool(!options.keep_branch));
+    // The checks run on the feature branch before the merge, the save after it does not run them again
+    let save_args = options.save_args.map(|mut save_args| {
+        save_args.push(String::from(FEATURE_SKIP_CHECKS));
+        if options.dry_run {
+            save_args.push(String::from(FEATURE_DRY_RUN));
+        }
+        save_args
+    });
+
+    if options.dry_run {
+        let mut message = format!("Dry run, nothing was changed! Would have {}", done);
+        if !checks.is_empty() {
+            message.push_str(format!("\nChecks that must pass on {} before the merge:", feature).as_str());
+            for check in checks.iter() {
+                message.push_str(format!("\n  - {}", check).as_str());
+            }
+        }
+        message.push_str(format!("\n{}", plan).as_str());
+        let mut report = report.field("steps_planned", Json::strs(plan.summaries()));
+        if let Some(save_args) = save_args {
+            let save_report = save_process(save_args.into_iter())?;
+            message.push_str(format!("\nThen save, planned from the current checkout:\n{}", save_report.message()).as_str());
+            report = report.field("save", save_report.to_json());
+        }
+        return Ok(report.with_message(message));
+    }
+    run_feature_checks(git, &checks, &feature)?;
+    plan.execute().on_error(format!(
+        "Finish stopped at the failed step, the steps after it were not run. Resolve any conflicts ('git status' lists them, 'git merge --abort' or 'git rebase --abort' cancels), then complete the steps by hand or run 'cargit finish {}' again",
+        feature
+    ))?;
+    let mut message = format!("Finished {}: {}", feature, done);
+    let mut report = report
+        .field("commit", Json::str(git.current_commit()?))
+        .field("steps_done", Json::strs(plan.summaries()));
+    if let Some(save_args) = save_args {
+        let save_report = save_process(save_args.into_iter()).on_error(format!("{} was finished, but the save after it failed", feature))?;
+        message.push_str(format!("\n{}", save_report.message()).as_str());
+        report = report.field("save", save_report.to_json());
+    }
+    return Ok(report.with_message(message));
+}
+
+fn process_finish_args<I>(args_iter: I) -> Result<FinishModeOptions, ErrorChain>
+where I: Iterator<Item = String> {
+    let mut options = FinishModeOptions { feature: None, into: None, strategy: None, commit_message: None, keep_branch: false, skip_checks: false, dry_run: false, save_args: None };
+    let mut args_iter = args_iter.peekable();
+    while let Some(next_arg) = args_iter.next() {
+        match next_arg.as_str() {
+            FEATURE_MERGE | FEATURE_SQUASH | FEATURE_REBASE => {
+                if options.strategy.is_some() {
+                    return Err(ErrorChain::new(format!("merge strategy ('{}' or '{}' or '{}') set more than once", FEATURE_MERGE, FEATURE_SQUASH, FEATURE_REBASE)));
+                }
+                options.strategy = MergeStrategy::parse(next_arg.trim_start_matches('-'));
+            }
+            FEATURE_INTO => options.into = Some(args_iter.next().on_error(format!("the '{}' option must be followed by a branch name", FEATURE_INTO))?),
+            FEATURE_WITH_MSG => options.commit_message = Some(args_iter.next().on_error(format!("the '{}' option must be followed by a commit message", FEATURE_WITH_MSG))?),
+            FEATURE_KEEP_BRANCH => options.keep_branch = true,
+            FEATURE_SKIP_CHECKS => options.skip_checks = true,
+            FEATURE_DRY_RUN => options.dry_run = true,
+            FEATURE_SAVE => {
+                options.save_args = Some(args_iter.by_ref().collect());
+            }
+            _ if take_output_format(&next_arg, &mut args_iter)? => {}
+            _ if next_arg.starts_with('-') || options.feature.is_some() => {
+                return Err(ErrorChain::new(format!("invalid argument passed to finish mode: '{}'", next_arg)));
+            }
+            _ => options.feature = Some(next_arg)
+        }
+    }
+    return Ok(options);
+}
+
+/// Runs the checks on the tree of `feature`, checking it out first when it is named from another branch,
+/// and going back there if they fail
+fn run_feature_checks(git: &dyn GitBackend, checks: &[Check], feature: &str) -> Result<(), ErrorChain> {
+    if checks.is_empty() {
+        return Ok(());
+    }
+    let original = match git.is_detatched_mode().on_error("could not verify head attatchement")? {
+        true => git.current_commit().on_error("could not read HEAD")?,
+        false => git.branch_name().on_error("could not get branch name")?
+    };
+    if original != feature {
+        git_checkout(feature).run().on_error(format!("could not check out {} to run the checks", feature))?;
+    }
+    let checked = run_checks(checks).on_error(format!("Finish aborted, fix the failures on {} or use '{}' to merge anyway", feature, FEATURE_SKIP_CHECKS));
+    if checked.is_err() && original != feature {
+        git_checkout(&original).run().on_error(format!("the checks failed, and could not check out {} again", original))?;
+    }
+    return checked;
+}
+
+/// The configured `main-branch`, or the first of `main` and `master` that exists locally
+fn main_branch(git: &dyn GitBackend, configured: Option<String>) -> Result<String, ErrorChain> {
+    if let Some(configured) = configured {
+        return Ok(configured);
+    }
+    for branch in DEFAULT_MAIN_BRANCHES {
+        if git.ref_exists(&format!("refs/heads/{}", branch))? {
+            return Ok(branch.to_owned());
+        }
+    }
+    return Err(usage_error(format!("could not find the main branch (none of {}), set 'main-branch' in the config or use '{}'/'{}'", DEFAULT_MAIN_BRANCHES.join(", "), FEATURE_FROM, FEATURE_INTO)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        return args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>().into_iter();
+    }
+
+    #[test]
+    fn test_finish_args() {
+        let options = process_finish_args(args(&["login", "--squash", "-m", "Add login", "--save", "minor", "-publish"])).unwrap();
+        assert_eq!(options.feature.as_deref(), Some("login"));
+        assert_eq!(options.strategy, Some(MergeStrategy::Squash));
+        assert_eq!(options.commit_message.as_deref(), Some("Add login"));
+        assert_eq!(options.save_args, Some(vec![String::from("minor"), String::from("-publish")]));
+        assert!(process_finish_args(args(&["--merge", "--rebase"])).is_err());
+        assert!(process_finish_args(args(&["one", "two"])).is_err());
+        assert!(process_finish_args(args(&["--into"])).is_err());
+    }
+
+    #[test]
+    fn test_main_branch() {
+        let git = FakeGit::new("master", &[("c1", &[], "init")]);
+        assert_eq!(main_branch(&git, None).unwrap(), "master");
+        assert_eq!(main_branch(&git, Some(String::from("develop"))).unwrap(), "develop");
+        assert!(main_branch(&FakeGit::new("trunk", &[("c1", &[], "init")]), None).is_err());
+    }
+}
@@ -0,0 +1,525 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use gmec::types::error_chain::ErrorChain;
+
+use crate::internal::*;
+
+/// Which parents of a merge commit a walk through history follows
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Parents {
+    /// Only the first parent: the commits made on the branch itself, with each merge standing in for the branch it merged
+    First,
+    /// Every parent, newest first by commit date, never listing a commit before one of its children
+    All
+}
+
+/// Read-only questions about the repository that save and load need answered. Commands that change
+/// the repository stay [`CliCommand`]s, so they can be planned, printed and rolled back
+pub(crate) trait GitBackend {
+    /// Hash of the checked out commit
+    fn current_commit(&self) -> Result<String, ErrorChain>;
+    /// Name of the checked out branch, `HEAD` when detatched
+    fn branch_name(&self) -> Result<String, ErrorChain>;
+    /// The branch HEAD was on before it was detatched, from the reflog
+    fn last_attatched_head_branch(&self) -> Result<String, ErrorChain>;
+    fn remote_names(&self) -> Result<Vec<String>, ErrorChain>;
+    /// Top directory of the checked out working tree
+    fn work_dir(&self) -> Result<PathBuf, ErrorChain>;
+    /// The git directory shared by every worktree of the repository
+    fn common_dir(&self) -> Result<PathBuf, ErrorChain>;
+    /// Paths of the linked worktrees (`git worktree add`), without the main working tree
+    fn linked_worktrees(&self) -> Result<Vec<PathBuf>, ErrorChain>;
+    /// Hashes of the commits reachable from `branch` through `parents`, newest first, produced as they are read
+    fn commit_history<'a>(&'a self, branch: &str, parents: Parents) -> Result<Box<dyn Iterator<Item = String> + 'a>, ErrorChain>;
+    /// The newest commit of `branch` (through `parents`) committed at or before `time` (a Unix timestamp), with its commit time
+    fn last_commit_before(&self, branch: &str, parents: Parents, time: i64) -> Result<Option<(String, i64)>, ErrorChain>;
+    /// Every tag, by the hash of the commit it points to
+    fn tags_by_commit(&self) -> Result<HashMap<String, Vec<String>>, ErrorChain>;
+    /// Whether `id` names anything git can check out (a commit hash, tag or branch)
+    fn ref_exists(&self, id: &str) -> Result<bool, ErrorChain>;
+    fn tag_exists(&self, tag: &str) -> Result<bool, ErrorChain>;
+    /// Full messages of the non-merge commits after `since` (or of the whole history) that touch `path`, newest first
+    fn commit_messages_since(&self, since: Option<&str>, path: &str) -> Result<Vec<String>, ErrorChain>;
+    /// Whether anything under `path` differs from `rev`: committed, staged, unstaged or untracked
+    fn has_changes_since(&self, rev: &str, path: &str) -> Result<bool, ErrorChain>;
+    /// Whether tracked files have staged or unstaged changes, untracked files do not count
+    fn has_uncommitted_changes(&self) -> Result<bool, ErrorChain>;
+    /// (`stash@{n}`, message) of each stash entry, newest first, eg. `On main: <message given to git stash push>`
+    fn stashes(&self) -> Result<Vec<(String, String)>, ErrorChain>;
+    /// How many commits are reachable from `until` but not from `since`, like `git rev-list --count since..until`
+    fn count_commits(&self, since: &str, until: &str) -> Result<usize, ErrorChain>;
+    /// Staged, unstaged and untracked files in `git status --short` form, eg. ` M src/lib.rs` or `?? notes.txt`
+    fn changed_files(&self) -> Result<Vec<String>, ErrorChain>;
+    /// (author name, commit time as a Unix timestamp, first line of the message) of `commit`
+    fn commit_summary(&self, commit: &str) -> Result<(String, i64, String), ErrorChain>;
+
+    fn is_detatched_mode(&self) -> Result<bool, ErrorChain> {
+        return Ok(self.branch_name()? == "HEAD"); // branches CANNOT be named HEAD
+    }
+
+    /// The remote to push to when none is configured: `origin` if it exists, otherwise the first remote
+    fn remote_name(&self) -> Result<Option<String>, ErrorChain> {
+        let remote_names = self.remote_names()?;
+        if remote_names.iter().any(|remote_name| remote_name == "origin") {
+            return Ok(Some(String::from("origin")));
+        }
+        return Ok(remote_names.into_iter().next());
+    }
+}
+
+/// Opens the backend chosen at build time: libgit2 with the `native-git` feature, the `git` executable
+/// otherwise (or when libgit2 cannot open the repository, so `git` can report why)
+pub(crate) fn open_git_backend() -> Result<Box<dyn GitBackend>, ErrorChain> {
+    #[cfg(feature = "native-git")]
+    if let Ok(native_git) = native::NativeGit::open() {
+        return Ok(Box::new(native_git));
+    }
+    return Ok(Box::new(CliGit));
+}
+
+/// Answers every question by running `git` and parsing its output
+pub(crate) struct CliGit;
+
+impl GitBackend for CliGit {
+    fn current_commit(&self) -> Result<String, ErrorChain> {
+        return get_current_commit();
+    }
+
+    fn branch_name(&self) -> Result<String, ErrorChain> {
+        return get_branch_name();
+    }
+
+    fn last_attatched_head_branch(&self) -> Result<String, ErrorChain> {
+        return last_attatched_head_branch();
+    }
+
+    fn remote_names(&self) -> Result<Vec<String>, ErrorChain> {
+        return get_remote_names();
+    }
+
+    fn work_dir(&self) -> Result<PathBuf, ErrorChain> {
+        return get_work_dir();
+    }
+
+    fn common_dir(&self) -> Result<PathBuf, ErrorChain> {
+        return get_common_dir();
+    }
+
+    fn linked_worktrees(&self) -> Result<Vec<PathBuf>, ErrorChain> {
+        return get_linked_worktrees();
+    }
+
+    fn commit_history<'a>(&'a self, branch: &str, parents: Parents) -> Result<Box<dyn Iterator<Item = String> + 'a>, ErrorChain> {
+        return Ok(Box::new(stream_commits_in_branch(branch, parents)?));
+    }
+
+    fn last_commit_before(&self, branch: &str, parents: Parents, time: i64) -> Result<Option<(String, i64)>, ErrorChain> {
+        return get_last_commit_before(branch, parents, time);
+    }
+
+    fn tags_by_commit(&self) -> Result<HashMap<String, Vec<String>>, ErrorChain> {
+        return get_tags_by_commit();
+    }
+
+    fn ref_exists(&self, id: &str) -> Result<bool, ErrorChain> {
+        return git_ref_exists(id);
+    }
+
+    fn tag_exists(&self, tag: &str) -> Result<bool, ErrorChain> {
+        return git_tag_exists(tag);
+    }
+
+    fn commit_messages_since(&self, since: Option<&str>, path: &str) -> Result<Vec<String>, ErrorChain> {
+        return get_commit_messages_since(since, path);
+    }
+
+    fn has_changes_since(&self, rev: &str, path: &str) -> Result<bool, ErrorChain> {
+        return git_has_changes_since(rev, path);
+    }
+
+    fn has_uncommitted_changes(&self) -> Result<bool, ErrorChain> {
+        return git_has_uncommitted_changes();
+    }
+
+    fn stashes(&self) -> Result<Vec<(String, String)>, ErrorChain> {
+        return get_stashes();
+    }
+
+    fn count_commits(&self, since: &str, until: &str) -> Result<usize, ErrorChain> {
+        return git_count_commits(since, until);
+    }
+
+    fn changed_files(&self) -> Result<Vec<String>, ErrorChain> {
+        return get_changed_files();
+    }
+
+    fn commit_summary(&self, commit: &str) -> Result<(String, i64, String), ErrorChain> {
+        return get_commit_summary(commit);
+    }
+}
+
+#[cfg(feature = "native-git")]
+mod native {
+    use std::{collections::HashMap, fs, path::{Path, PathBuf}};
+
+    use git2::{BranchType, DiffOptions, Oid, Repository, Sort, Status, StatusOptions, Tree};
+    use gmec::types::error_chain::{ErrorChain, ErrorPropogation};
+
+    use crate::internal::find_detatched_from_branch;
+
+    use super::{GitBackend, Parents};
+
+    /// Answers every question with libgit2, without starting any process
+    pub(crate) struct NativeGit {
+        repo: Repository
+    }
+
+    impl NativeGit {
+        pub fn open() -> Result<NativeGit, ErrorChain> {
+            let repo = Repository::discover(".").on_error("not inside a git repository")?;
+            return Ok(NativeGit { repo });
+        }
+
+        fn resolve(&self, rev: &str) -> Result<Oid, ErrorChain> {
+            let object = self.repo.revparse_single(rev).on_error(format!("'{}' is not a commit, tag or branch", rev))?;
+            return Ok(object.peel_to_commit().on_error(format!("'{}' does not point to a commit", rev))?.id());
+        }
+
+        /// `path` (relative to the current directory) as a pathspec relative to the repository root,
+        /// `None` when it is the whole repository
+        fn pathspec(&self, path: &str) -> Result<Option<String>, ErrorChain> {
+            let workdir = self.repo.workdir().on_error("the repository has no working directory")?;
+            let workdir = fs::canonicalize(workdir).on_error(format!("could not resolve {}", workdir.display()))?;
+            let path = fs::canonicalize(Path::new(path)).on_error(format!("could not resolve {}", path))?;
+            let relative = path.strip_prefix(&workdir).on_error(format!("{} is outside of the repository", path.display()))?;
+            if relative.as_os_str().is_empty() {
+                return Ok(None);
+            }
+            return Ok(Some(relative.to_string_lossy().replace('\\', "/")));
+        }
+
+        fn diff_options(pathspec: &Option<String>) -> DiffOptions {
+            let mut options = DiffOptions::new();
+            if let Some(pathspec) = pathspec {
+                options.pathspec(pathspec);
+            }
+            return options;
+        }
+
+        /// Whether `tree` differs from the tree of the commit's first parent under `pathspec`
+        fn touches(&self, tree: &Tree, parent_tree: Option<&Tree>, pathspec: &Option<String>) -> Result<bool, ErrorChain> {
+            if pathspec.is_none() {
+                return Ok(parent_tree.map(|parent_tree| parent_tree.id() != tree.id()).unwrap_or(true));
+            }
+            let diff = self.repo.diff_tree_to_tree(parent_tree, Some(tree), Some(&mut NativeGit::diff_options(pathspec))).on_error("could not diff commit")?;
+            return Ok(diff.deltas().len() > 0);
+        }
+    }
+
+    impl GitBackend for NativeGit {
+        fn current_commit(&self) -> Result<String, ErrorChain> {
+            let head = self.repo.head().on_error("could not read HEAD")?;
+            return Ok(head.peel_to_commit().on_error("HEAD does not point to a commit")?.id().to_string());
+        }
+
+        fn branch_name(&self) -> Result<String, ErrorChain> {
+            if self.repo.head_detached().on_error("could not read HEAD")? {
+                return Ok(String::from("HEAD"));
+            }
+            let head = self.repo.head().on_error("could not read HEAD")?;
+            return Ok(head.shorthand().on_error("branch name is not valid UTF-8")?.to_owned());
+        }
+
+        fn last_attatched_head_branch(&self) -> Result<String, ErrorChain> {
+            let reflog = self.repo.reflog("HEAD").on_error("could not read the reflog of HEAD")?;
+            let messages: Vec<String> = reflog.iter().filter_map(|entry| entry.message().map(|message| message.to_owned())).collect();
+            let is_local_branch = |name: &str| self.repo.find_branch(name, BranchType::Local).is_ok();
+            return find_detatched_from_branch(messages.iter().map(|message| message.as_str()), is_local_branch)
+                .on_error("could not locate where HEAD was detatched from branch");
+        }
+
+        fn remote_names(&self) -> Result<Vec<String>, ErrorChain> {
+            let remotes = self.repo.remotes().on_error("could not list remotes")?;
+            return Ok(remotes.iter().flatten().map(|remote| remote.to_owned()).collect());
+        }
+
+        fn work_dir(&self) -> Result<PathBuf, ErrorChain> {
+            let work_dir = self.repo.workdir().on_error("the repository has no working directory")?;
+            return Ok(work_dir.components().collect());
+        }
+
+        fn common_dir(&self) -> Result<PathBuf, ErrorChain> {
+            return Ok(self.repo.commondir().components().collect());
+        }
+
+        fn linked_worktrees(&self) -> Result<Vec<PathBuf>, ErrorChain> {
+            let mut worktrees = Vec::new();
+            for name in self.repo.worktrees().on_error("could not list worktrees")?.iter().flatten() {
+                let worktree = self.repo.find_worktree(name).on_error(format!("could not open worktree {}", name))?;
+                worktrees.push(worktree.path().components().collect());
+            }
+            return Ok(worktrees);
+        }
+
+        fn commit_history<'a>(&'a self, branch: &str, parents: Parents) -> Result<Box<dyn Iterator<Item = String> + 'a>, ErrorChain> {
+            let mut revwalk = self.repo.revwalk().on_error("could not walk history")?;
+            revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME).on_error("could not sort history")?;
+            if parents == Parents::First {
+                revwalk.simplify_first_parent().on_error("could not follow first parents")?;
+            }
+            revwalk.push(self.resolve(branch)?).on_error(format!("could not walk the history of {}", branch))?;
+            return Ok(Box::new(revwalk.map_while(|oid| oid.ok()).map(|oid| oid.to_string())));
+        }
+
+        fn last_commit_before(&self, branch: &str, parents: Parents, time: i64) -> Result<Option<(String, i64)>, ErrorChain> {
+            for commit in self.commit_history(branch, parents)? {
+                let oid = Oid::from_str(&commit).on_error("could not read commit id")?;
+                let commit_time = self.repo.find_commit(oid).on_error(format!("could not read commit {}", commit))?.time().seconds();
+                if commit_time <= time {
+                    return Ok(Some((commit, commit_time)));
+                }
+            }
+            return Ok(None);
+        }
+
+        fn tags_by_commit(&self) -> Result<HashMap<String, Vec<String>>, ErrorChain> {
+            let mut tags_by_commit: HashMap<String, Vec<String>> = HashMap::new();
+            for reference in self.repo.references_glob("refs/tags/*").on_error("could not list tags")? {
+                let reference = reference.on_error("could not read tag")?;
+                let (tag, commit) = match (reference.shorthand(), reference.peel_to_commit()) {
+                    (Some(tag), Ok(commit)) => (tag.to_owned(), commit.id().to_string()),
+                    _ => continue
+                };
+                tags_by_commit.entry(commit).or_default().push(tag);
+            }
+            return Ok(tags_by_commit);
+        }
+
+        fn ref_exists(&self, id: &str) -> Result<bool, ErrorChain> {
+            return Ok(self.resolve(id).is_ok());
+        }
+
+        fn tag_exists(&self, tag: &str) -> Result<bool, ErrorChain> {
+            return Ok(self.repo.find_reference(&format!("refs/tags/{}", tag)).is_ok());
+        }
+
+        fn commit_messages_since(&self, since: Option<&str>, path: &str) -> Result<Vec<String>, ErrorChain> {
+            let pathspec = self.pathspec(path)?;
+            let mut revwalk = self.repo.revwalk().on_error("could not walk history")?;
+            revwalk.set_sorting(Sort::TIME).on_error("could not sort history")?;
+            revwalk.push_head().on_error("could not walk the history of HEAD")?;
+            if let Some(since) = since {
+                revwalk.hide(self.resolve(since)?).on_error(format!("could not exclude the history of {}", since))?;
+            }
+            let mut messages = Vec::new();
+            for oid in revwalk {
+                let commit = self.repo.find_commit(oid.on_error("could not read commit")?).on_error("could not read commit")?;
+                if commit.parent_count() > 1 {
+                    continue;
+                }
+                let tree = commit.tree().on_error("could not read commit tree")?;
+                let parent_tree = match commit.parent(0) {
+                    Ok(parent) => Some(parent.tree().on_error("could not read parent tree")?),
+                    Err(_) => None
+                };
+                if self.touches(&tree, parent_tree.as_ref(), &pathspec)? {
+                    messages.push(commit.message().unwrap_or_default().trim().to_owned());
+                }
+            }
+            return Ok(messages);
+        }
+
+        fn has_changes_since(&self, rev: &str, path: &str) -> Result<bool, ErrorChain> {
+            let pathspec = self.pathspec(path)?;
+            let tree = self.repo.find_commit(self.resolve(rev)?).on_error(format!("could not read {}", rev))?.tree().on_error(format!("could not read the tree of {}", rev))?;
+            let mut options = NativeGit::diff_options(&pathspec);
+            options.include_untracked(true).recurse_untracked_dirs(true);
+            let diff = self.repo.diff_tree_to_workdir_with_index(Some(&tree), Some(&mut options)).on_error(format!("could not diff against {}", rev))?;
+            return Ok(diff.deltas().len() > 0);
+        }
+
+        fn has_uncommitted_changes(&self) -> Result<bool, ErrorChain> {
+            let mut options = StatusOptions::new();
+            options.include_untracked(false).include_ignored(false);
+            let statuses = self.repo.statuses(Some(&mut options)).on_error("could not read the status of the working tree")?;
+            return Ok(!statuses.is_empty());
+        }
+
+        fn stashes(&self) -> Result<Vec<(String, String)>, ErrorChain> {
+            if self.repo.find_reference("refs/stash").is_err() {
+                return Ok(Vec::new());
+            }
+            let reflog = self.repo.reflog("refs/stash").on_error("could not read the stash list")?;
+            return Ok(reflog.iter().enumerate().map(|(index, entry)| (format!("stash@{{{}}}", index), entry.message().unwrap_or_default().to_owned())).collect());
+        }
+
+        fn count_commits(&self, since: &str, until: &str) -> Result<usize, ErrorChain> {
+            let mut revwalk = self.repo.revwalk().on_error("could not walk the history")?;
+            revwalk.push(self.resolve(until)?).on_error(format!("could not walk the history of {}", until))?;
+            revwalk.hide(self.resolve(since)?).on_error(format!("could not walk the history of {}", since))?;
+            return Ok(revwalk.count());
+        }
+
+        fn changed_files(&self) -> Result<Vec<String>, ErrorChain> {
+            let mut options = StatusOptions::new();
+            options.include_untracked(true).recurse_untracked_dirs(true).include_ignored(false);
+            let statuses = self.repo.statuses(Some(&mut options)).on_error("could not read the status of the working tree")?;
+            return Ok(statuses.iter().map(|entry| format!("{} {}", short_status(entry.status()), entry.path().unwrap_or_default())).collect());
+        }
+
+        fn commit_summary(&self, commit: &str) -> Result<(String, i64, String), ErrorChain> {
+            let object = self.repo.revparse_single(commit).on_error(format!("could not find commit {}", commit))?;
+            let commit = object.peel_to_commit().on_error(format!("{} is not a commit", commit))?;
+            let author = commit.author().name().unwrap_or_default().to_owned();
+            return Ok((author, commit.time().seconds(), commit.summary().unwrap_or_default().to_owned()));
+        }
+    }
+
+    /// The two letter code `git status --short` shows for `status`: the index, then the working tree
+    fn short_status(status: Status) -> String {
+        if status.contains(Status::WT_NEW) {
+            return String::from("??");
+        }
+        let index = match status {
+            status if status.contains(Status::INDEX_NEW) => 'A',
+            status if status.contains(Status::INDEX_MODIFIED) => 'M',
+            status if status.contains(Status::INDEX_DELETED) => 'D',
+            status if status.contains(Status::INDEX_RENAMED) => 'R',
+            status if status.contains(Status::INDEX_TYPECHANGE) => 'T',
+            _ => ' '
+        };
+        let work_tree = match status {
+            status if status.contains(Status::WT_MODIFIED) => 'M',
+            status if status.contains(Status::WT_DELETED) => 'D',
+            status if status.contains(Status::WT_RENAMED) => 'R',
+            status if status.contains(Status::WT_TYPECHANGE) => 'T',
+            status if status.contains(Status::CONFLICTED) => 'U',
+            _ => ' '
+        };
+        return format!("{}{}", index, work_tree);
+    }
+}
+
+/// An in-memory repository: a single linear branch, newest commit first
+#[cfg(test)]
+pub(crate) struct FakeGit {
+    pub branch: String,
+    /// (hash, tags, message) of each commit
+    pub commits: Vec<(String, Vec<String>, String)>,
+    /// Index in `commits` of the checked out commit
+    pub head: usize,
+    pub detatched: bool,
+    pub dirty: bool,
+    /// (`stash@{n}`, message) of each stash entry, newest first
+    pub stashes: Vec<(String, String)>
+}
+
+#[cfg(test)]
+impl FakeGit {
+    pub fn new(branch: &str, commits: &[(&str, &[&str], &str)]) -> FakeGit {
+        let commits = commits.iter()
+            .map(|(hash, tags, message)| (hash.to_string(), tags.iter().map(|tag| tag.to_string()).collect(), message.to_string()))
+            .collect();
+        return FakeGit { branch: branch.to_owned(), commits, head: 0, detatched: false, dirty: false, stashes: Vec::new() };
+    }
+
+    /// Commits are a day apart, the oldest made on day 1 (`86_400`)
+    pub fn commit_time(&self, index: usize) -> i64 {
+        return (self.commits.len() - index) as i64 * 86_400;
+    }
+
+    fn index_of(&self, rev: &str) -> Option<usize> {
+        let rev = rev.strip_prefix("refs/heads/").unwrap_or(rev);
+        if rev == self.branch || rev == "HEAD" {
+            return Some(if rev == "HEAD" { self.head } else { 0 });
+        }
+        return self.commits.iter().position(|(hash, tags, _)| hash == rev || tags.iter().any(|tag| tag == rev));
+    }
+}
+
+#[cfg(test)]
+impl GitBackend for FakeGit {
+    fn current_commit(&self) -> Result<String, ErrorChain> {
+        return Ok(self.commits[self.head].0.clone());
+    }
+
+    fn branch_name(&self) -> Result<String, ErrorChain> {
+        return Ok(if self.detatched { String::from("HEAD") } else { self.branch.clone() });
+    }
+
+    fn last_attatched_head_branch(&self) -> Result<String, ErrorChain> {
+        return Ok(self.branch.clone());
+    }
+
+    fn remote_names(&self) -> Result<Vec<String>, ErrorChain> {
+        return Ok(vec![String::from("origin")]);
+    }
+
+    fn work_dir(&self) -> Result<PathBuf, ErrorChain> {
+        return Ok(PathBuf::from("/repo"));
+    }
+
+    fn common_dir(&self) -> Result<PathBuf, ErrorChain> {
+        return Ok(PathBuf::from("/repo/.git"));
+    }
+
+    fn linked_worktrees(&self) -> Result<Vec<PathBuf>, ErrorChain> {
+        return Ok(Vec::new());
+    }
+
+    fn commit_history<'a>(&'a self, branch: &str, _parents: Parents) -> Result<Box<dyn Iterator<Item = String> + 'a>, ErrorChain> {
+        let start = self.index_of(branch).ok_or_else(|| ErrorChain::new(format!("unknown branch {}", branch)))?;
+        return Ok(Box::new(self.commits[start..].iter().map(|(hash, _, _)| hash.clone())));
+    }
+
+    fn last_commit_before(&self, branch: &str, parents: Parents, time: i64) -> Result<Option<(String, i64)>, ErrorChain> {
+        let start = self.index_of(branch).ok_or_else(|| ErrorChain::new(format!("unknown branch {}", branch)))?;
+        let commits = self.commit_history(branch, parents)?;
+        return Ok(commits.zip(start..).map(|(hash, index)| (hash, self.commit_time(index))).find(|(_, commit_time)| *commit_time <= time));
+    }
+
+    fn tags_by_commit(&self) -> Result<HashMap<String, Vec<String>>, ErrorChain> {
+        return Ok(self.commits.iter().filter(|(_, tags, _)| !tags.is_empty()).map(|(hash, tags, _)| (hash.clone(), tags.clone())).collect());
+    }
+
+    fn ref_exists(&self, id: &str) -> Result<bool, ErrorChain> {
+        return Ok(self.index_of(id).is_some());
+    }
+
+    fn tag_exists(&self, tag: &str) -> Result<bool, ErrorChain> {
+        return Ok(self.commits.iter().any(|(_, tags, _)| tags.iter().any(|existing| existing == tag)));
+    }
+
+    fn commit_messages_since(&self, since: Option<&str>, _path: &str) -> Result<Vec<String>, ErrorChain> {
+        let end = since.and_then(|since| self.index_of(since)).unwrap_or(self.commits.len());
+        return Ok(self.commits[self.head..end].iter().map(|(_, _, message)| message.clone()).collect());
+    }
+
+    fn has_changes_since(&self, rev: &str, _path: &str) -> Result<bool, ErrorChain> {
+        return Ok(self.index_of(rev) != Some(self.head));
+    }
+
+    fn has_uncommitted_changes(&self) -> Result<bool, ErrorChain> {
+        return Ok(self.dirty);
+    }
+
+    fn stashes(&self) -> Result<Vec<(String, String)>, ErrorChain> {
+        return Ok(self.stashes.clone());
+    }
+
+    fn count_commits(&self, since: &str, until: &str) -> Result<usize, ErrorChain> {
+        let since = self.index_of(since).ok_or_else(|| ErrorChain::new(format!("unknown revision {}", since)))?;
+        let until = self.index_of(until).ok_or_else(|| ErrorChain::new(format!("unknown revision {}", until)))?;
+        return Ok(since.saturating_sub(until));
+    }
+
+    fn changed_files(&self) -> Result<Vec<String>, ErrorChain> {
+        return Ok(if self.dirty { vec![String::from(" M src/lib.rs")] } else { Vec::new() });
+    }
+
+    fn commit_summary(&self, commit: &str) -> Result<(String, i64, String), ErrorChain> {
+        let index = self.index_of(commit).ok_or_else(|| ErrorChain::new(format!("unknown revision {}", commit)))?;
+        let subject = self.commits[index].2.lines().next().unwrap_or_default().to_owned();
+        return Ok((String::from("Test Author"), self.commit_time(index), subject));
+    }
+}
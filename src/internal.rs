@@ -1,51 +1,416 @@
-use std::{process::{Command, Output}, ffi::OsStr, io::{self, Write}};
+use std::{process::{Child, ChildStdout, Command, Output, Stdio}, ffi::OsStr, path::{Path, PathBuf}, io::{self, BufRead, BufReader, Lines, Read, Write}, cmp::Ordering, collections::HashMap, fmt::{self, Display}, sync::OnceLock};
 
 use gmec::{patterns::PatternMatcher, types::error_chain::{ErrorChain, ErrorPropogation}};
 
+use crate::git::*;
+use crate::report::*;
+
 pub(crate) const CARGO_MANIFEST: &str = "Cargo.toml";
-pub(crate) const VERSION_PREFIX: &str = "version = \"";
 
+pub(crate) type VersionParts<'a> = (&'a str, &'a str, &'a str, Option<&'a str>, Option<&'a str>);
+
+pub(crate) const DEFAULT_TAG_TEMPLATE: &str = "{version}";
+pub(crate) const DEFAULT_WORKSPACE_TAG_TEMPLATE: &str = "{crate}-v{version}";
+pub(crate) const DEFAULT_MSG: &str = "(undocumented change)";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub(crate) enum VersionPart {
     Major,
     Minor,
     Patch
 }
 
-#[derive(Clone, Copy)]
+impl VersionPart {
+    pub fn parse(part: &str) -> Option<VersionPart> {
+        match part.to_lowercase().as_str() {
+            "major" => Some(VersionPart::Major),
+            "minor" => Some(VersionPart::Minor),
+            "patch" => Some(VersionPart::Patch),
+            _ => None
+        }
+    }
+
+    /// Orders parts by how big a change they announce, `Patch` lowest
+    pub fn significance(&self) -> u8 {
+        match self {
+            VersionPart::Major => 2,
+            VersionPart::Minor => 1,
+            VersionPart::Patch => 0
+        }
+    }
+
+    /// The part to bump for a change of this size under Cargo's rules, where the first non-zero part is the
+    /// "major" one: breaking changes bump minor in `0.y.z`, and every change bumps patch in `0.0.z`
+    pub fn for_version(&self, current: &Version) -> VersionPart {
+        match (current.major, current.minor, self) {
+            (0, 0, _) => VersionPart::Patch,
+            (0, _, VersionPart::Major) => VersionPart::Minor,
+            (0, _, _) => VersionPart::Patch,
+            (_, _, part) => *part
+        }
+    }
+}
+
+impl Display for VersionPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionPart::Major => write!(f, "major"),
+            VersionPart::Minor => write!(f, "minor"),
+            VersionPart::Patch => write!(f, "patch")
+        }
+    }
+}
+
+/// How a commit made by cargit is undone when rolling back a failed save
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum ResetMode {
+    /// Keep the committed changes in the working tree
+    Soft,
+    /// Discard the committed changes entirely
+    Hard
+}
+
+impl ResetMode {
+    pub fn parse(mode: &str) -> Option<ResetMode> {
+        match mode.to_lowercase().as_str() {
+            "soft" => Some(ResetMode::Soft),
+            "hard" => Some(ResetMode::Hard),
+            _ => None
+        }
+    }
+}
+
+/// How finish brings a feature branch into the main branch
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum MergeStrategy {
+    /// A merge commit, or a fast-forward when the main branch has not moved
+    Merge,
+    /// One new commit on the main branch with every change of the feature branch
+    Squash,
+    /// Replay the feature commits on top of the main branch, then fast-forward it
+    Rebase
+}
+
+impl MergeStrategy {
+    pub fn parse(strategy: &str) -> Option<MergeStrategy> {
+        match strategy.to_lowercase().as_str() {
+            "merge" => Some(MergeStrategy::Merge),
+            "squash" => Some(MergeStrategy::Squash),
+            "rebase" => Some(MergeStrategy::Rebase),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MergeStrategy::Merge => "merge",
+            MergeStrategy::Squash => "squash",
+            MergeStrategy::Rebase => "rebase"
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) enum PreReleaseIdentifier {
+    Numeric(u64),
+    AlphaNumeric(String)
+}
+
+impl PreReleaseIdentifier {
+    pub fn parse(identifier: &str) -> Result<PreReleaseIdentifier, ErrorChain> {
+        if !is_valid_version_identifier(identifier) {
+            return Err(ErrorChain::new(format!("invalid pre-release identifier '{}'", identifier)));
+        }
+        if identifier.chars().all(|c| c.is_ascii_digit()) {
+            if identifier.len() > 1 && identifier.starts_with('0') {
+                return Err(ErrorChain::new(format!("numeric pre-release identifier '{}' must not have leading zeros", identifier)));
+            }
+            let number = identifier.parse().on_error(format!("error parsing pre-release identifier '{}' into u64", identifier))?;
+            return Ok(PreReleaseIdentifier::Numeric(number));
+        }
+        return Ok(PreReleaseIdentifier::AlphaNumeric(identifier.to_owned()));
+    }
+}
+
+impl Ord for PreReleaseIdentifier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (PreReleaseIdentifier::Numeric(a), PreReleaseIdentifier::Numeric(b)) => a.cmp(b),
+            (PreReleaseIdentifier::Numeric(_), PreReleaseIdentifier::AlphaNumeric(_)) => Ordering::Less,
+            (PreReleaseIdentifier::AlphaNumeric(_), PreReleaseIdentifier::Numeric(_)) => Ordering::Greater,
+            (PreReleaseIdentifier::AlphaNumeric(a), PreReleaseIdentifier::AlphaNumeric(b)) => a.cmp(b),
+        }
+    }
+}
+
+impl PartialOrd for PreReleaseIdentifier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for PreReleaseIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreReleaseIdentifier::Numeric(number) => write!(f, "{}", number),
+            PreReleaseIdentifier::AlphaNumeric(string) => write!(f, "{}", string),
+        }
+    }
+}
+
+/// A full SemVer 2.0 version. Ordering follows SemVer precedence, with build
+/// metadata only used as a final tie-breaker so that `Ord` agrees with `Eq`
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub(crate) struct Version {
     pub major: u32,
     pub minor: u32,
-    pub patch: u32
+    pub patch: u32,
+    pub pre: Vec<PreReleaseIdentifier>,
+    pub build: Vec<String>
 }
 
 impl Version {
-    pub fn to_string(&self) -> String {
-        format!("{}.{}.{}", self.major, self.minor, self.patch)
+    pub fn new(major: u32, minor: u32, patch: u32) -> Version {
+        return Version { major, minor, patch, pre: Vec::new(), build: Vec::new() }
+    }
+
+    pub fn parse(version_string: &str) -> Result<Version, ErrorChain> {
+        let (major, minor, patch, pre, build) = split_parts_from_version(version_string.trim())?;
+        let mut version = Version::new(
+            parse_version_number(major, "major")?,
+            parse_version_number(minor, "minor")?,
+            parse_version_number(patch, "patch")?,
+        );
+        if let Some(pre) = pre {
+            for identifier in pre.split('.') {
+                version.pre.push(PreReleaseIdentifier::parse(identifier)?);
+            }
+        }
+        if let Some(build) = build {
+            for identifier in build.split('.') {
+                if !is_valid_version_identifier(identifier) {
+                    return Err(ErrorChain::new(format!("invalid build metadata identifier '{}'", identifier)));
+                }
+                version.build.push(identifier.to_owned());
+            }
+        }
+        return Ok(version);
+    }
+
+    pub fn is_pre_release(&self) -> bool {
+        return !self.pre.is_empty();
+    }
+
+    /// Label of the pre-release (the first identifier, eg. `beta` in `1.0.0-beta.2`)
+    pub fn pre_release_label(&self) -> Option<&str> {
+        match self.pre.first() {
+            Some(PreReleaseIdentifier::AlphaNumeric(label)) => Some(label.as_str()),
+            _ => None
+        }
+    }
+
+    /// Increments the given part following the usual SemVer rules: lesser parts are set to 0,
+    /// and a pre-release of the resulting version (eg. `2.0.0-rc.1` for `major`) is simply released
+    pub fn increment(&self, part: &VersionPart) -> Version {
+        let mut new_ver = Version::new(self.major, self.minor, self.patch);
+        match part {
+            VersionPart::Major => {
+                if !(self.is_pre_release() && self.minor == 0 && self.patch == 0) {
+                    new_ver.major += 1;
+                }
+                new_ver.minor = 0;
+                new_ver.patch = 0;
+            }
+            VersionPart::Minor => {
+                if !(self.is_pre_release() && self.patch == 0) {
+                    new_ver.minor += 1;
+                }
+                new_ver.patch = 0;
+            }
+            VersionPart::Patch => {
+                if !self.is_pre_release() {
+                    new_ver.patch += 1;
+                }
+            }
+        }
+        return new_ver;
+    }
+
+    /// Moves to the next pre-release with the given label. A part to bump first may be supplied
+    /// (`1.2.3` + `major` + `rc` -> `2.0.0-rc.1`), otherwise the counter of a matching label is
+    /// incremented (`1.0.0-beta.1` -> `1.0.0-beta.2`), a different label restarts the counter
+    /// (`1.0.0-beta.2` -> `1.0.0-rc.1`) and a release version gets its patch bumped first
+    pub fn next_pre_release(&self, part: Option<&VersionPart>, label: &str) -> Version {
+        let (mut new_ver, counter) = match part {
+            Some(part) => {
+                let mut bumped = Version::new(self.major, self.minor, self.patch);
+                match part {
+                    VersionPart::Major => bumped = Version::new(self.major + 1, 0, 0),
+                    VersionPart::Minor => bumped = Version::new(self.major, self.minor + 1, 0),
+                    VersionPart::Patch => bumped.patch += 1,
+                }
+                (bumped, 1)
+            }
+            None if !self.is_pre_release() => (Version::new(self.major, self.minor, self.patch + 1), 1),
+            None => {
+                let base = Version::new(self.major, self.minor, self.patch);
+                match (self.pre_release_label() == Some(label), self.pre.last()) {
+                    (true, Some(PreReleaseIdentifier::Numeric(counter))) => (base, counter + 1),
+                    _ => (base, 1)
+                }
+            }
+        };
+        new_ver.pre.push(PreReleaseIdentifier::AlphaNumeric(label.to_owned()));
+        new_ver.pre.push(PreReleaseIdentifier::Numeric(counter));
+        return new_ver;
+    }
+
+    /// Strips pre-release and build identifiers (`1.0.0-rc.3` -> `1.0.0`)
+    pub fn release(&self) -> Version {
+        return Version::new(self.major, self.minor, self.patch);
+    }
+
+    /// The most significant part that changed from `older` to this version
+    pub fn bumped_part(&self, older: &Version) -> VersionPart {
+        if self.major != older.major {
+            return VersionPart::Major;
+        }
+        if self.minor != older.minor {
+            return VersionPart::Minor;
+        }
+        return VersionPart::Patch;
     }
 }
 
-pub(crate) fn cli_affirmative(string: String) -> bool {
-    match string.to_lowercase().trim() {
-        "y" | "ye" | "yes" => true,
-        _ => false
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        return self.major.cmp(&other.major)
+            .then(self.minor.cmp(&other.minor))
+            .then(self.patch.cmp(&other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.cmp(&other.pre)
+            })
+            .then_with(|| self.build.cmp(&other.build));
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if !self.pre.is_empty() {
+            let pre: Vec<String> = self.pre.iter().map(|identifier| identifier.to_string()).collect();
+            write!(f, "-{}", pre.join("."))?;
+        }
+        if !self.build.is_empty() {
+            write!(f, "+{}", self.build.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+/// Fills in the `{version}` and `{crate}` placeholders of a tag template (eg. `{crate}-v{version}`)
+pub(crate) fn render_tag_template(template: &str, crate_name: &str, version: &Version) -> String {
+    return template.replace("{version}", &version.to_string()).replace("{crate}", crate_name);
+}
+
+/// The naming of the version tags of one crate, used both to create tags and to recognise them
+#[derive(Clone, Debug)]
+pub(crate) struct TagFormat {
+    pub template: String,
+    pub crate_name: String
+}
+
+impl TagFormat {
+    pub fn new(template: &str, crate_name: &str) -> TagFormat {
+        return TagFormat { template: template.to_owned(), crate_name: crate_name.to_owned() };
+    }
+
+    pub fn render(&self, version: &Version) -> String {
+        return render_tag_template(&self.template, &self.crate_name, version);
+    }
+
+    /// Extracts the version from a tag following this format (`v1.2.3` -> `1.2.3` for `v{version}`)
+    pub fn parse(&self, tag: &str) -> Option<Version> {
+        let template = self.template.replace("{crate}", &self.crate_name);
+        let (prefix, suffix) = template.split_once("{version}")?;
+        let version_str = tag.strip_prefix(prefix)?.strip_suffix(suffix)?;
+        return Version::parse(version_str).ok();
     }
+
+    /// The highest version among `tags` that follow this format
+    pub fn find_version<'a>(&self, tags: &'a [String]) -> Option<(&'a str, Version)> {
+        return tags.iter().filter_map(|tag| self.parse(tag).map(|version| (tag.as_str(), version))).max_by(|(_, a), (_, b)| a.cmp(b));
+    }
+}
+
+pub(crate) fn parse_version_number(number: &str, part_name: &str) -> Result<u32, ErrorChain> {
+    if number.len() > 1 && number.starts_with('0') {
+        return Err(ErrorChain::new(format!("{} version '{}' must not have leading zeros", part_name, number)));
+    }
+    return number.parse().on_error(format!("Error parsing {} version into u32", part_name));
+}
+
+pub(crate) fn is_valid_version_identifier(identifier: &str) -> bool {
+    return !identifier.is_empty() && identifier.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+}
+
+pub(crate) fn cli_affirmative(string: String) -> bool {
+    return matches!(string.to_lowercase().trim(), "y" | "ye" | "yes");
 }
 
-pub(crate) struct Commiterator {
-    index: usize,
-    commits_and_tags: Vec<(String, Vec<String>)>
+/// Streams the commits of a branch, newest first, each with the tags pointing at it. Commits are read
+/// from the backend only as they are iterated, the tags are all read once up front
+pub(crate) struct Commiterator<'a> {
+    commits: Box<dyn Iterator<Item = String> + 'a>,
+    tags_by_commit: HashMap<String, Vec<String>>
 }
 
-impl Iterator for Commiterator {
+impl Iterator for Commiterator<'_> {
     type Item = (String, Vec<String>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.index >= self.commits_and_tags.len() {
-            return None
-        }
-        let cloned = (self.commits_and_tags[self.index].0.clone(), self.commits_and_tags[self.index].1.clone());
-        self.index += 1;
-        return Some(cloned)
+        let commit = self.commits.next()?;
+        let tags = self.tags_by_commit.remove(&commit).unwrap_or_default();
+        return Some((commit, tags))
+    }
+}
+
+/// The lines `git` writes to stdout, read while it is still running. Dropping it early stops `git`
+pub(crate) struct CliLineStream {
+    child: Child,
+    lines: Lines<BufReader<ChildStdout>>
+}
+
+impl CliLineStream {
+    pub fn spawn(program: &str, args: &[&str]) -> Result<CliLineStream, ErrorChain> {
+        let full_command = collect_full_command(&program, args);
+        let mut child = Command::new(program).args(args).stdout(Stdio::piped()).stderr(Stdio::null()).spawn().on_error(format!("error running command: {}", full_command))?;
+        let stdout = child.stdout.take().on_error(format!("could not read the output of: {}", full_command))?;
+        return Ok(CliLineStream { child, lines: BufReader::new(stdout).lines() });
+    }
+}
+
+impl Iterator for CliLineStream {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        return self.lines.next()?.ok();
+    }
+}
+
+impl Drop for CliLineStream {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
     }
 }
 
@@ -56,21 +421,207 @@ where S: AsRef<str> + AsRef<OsStr> {
     return Ok(out_result);
 }
 
+/// Runs a command with its output shown as it is produced. Its stderr is also captured, so the error
+/// for a failed command ends with what it printed there, and the failure is recorded for the exit status
 pub(crate) fn run_cli<S>(program: S, args: &[S]) -> Result<(), ErrorChain>
 where S: AsRef<str> + AsRef<OsStr> {
     let full_command = collect_full_command(&program, args);
-    let success = Command::new(program).args(args).status().on_error(format!("error running command: {}", full_command))?.success();
-    if success {
+    let failure_kind = FailureKind::of_program(program.as_ref());
+    let stdout = match output_format() {
+        OutputFormat::Text => Stdio::inherit(),
+        OutputFormat::Json => Stdio::from(io::stderr())
+    };
+    let mut child = match Command::new(&program).args(args).stdout(stdout).stderr(Stdio::piped()).spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            record_failure(failure_kind);
+            return Err(ErrorChain::from(err, format!("error running command: {}", full_command)));
+        }
+    };
+    let mut captured = Vec::new();
+    if let Some(mut child_stderr) = child.stderr.take() {
+        let mut stderr = io::stderr();
+        let mut buffer = [0u8; 4096];
+        while let Ok(read @ 1..) = child_stderr.read(&mut buffer) {
+            let _ = stderr.write_all(&buffer[..read]);
+            captured.extend_from_slice(&buffer[..read]);
+        }
+    }
+    let status = child.wait().on_error(format!("error running command: {}", full_command))?;
+    if status.success() {
         return Ok(())
     }
-    return Err(ErrorChain::new("command exited with abnormal status"));
+    record_failure(failure_kind);
+    let status = match status.code() {
+        Some(code) => format!("exit code {}", code),
+        None => String::from("no exit code, killed by a signal")
+    };
+    return Err(ErrorChain::new(format!("command exited with abnormal status ({}){}", status, stderr_tail(&String::from_utf8_lossy(&captured)))));
+}
+
+/// The last lines a failed command wrote to stderr, indented below its error. Progress lines
+/// redrawn with `\r` only keep their final state
+fn stderr_tail(stderr: &str) -> String {
+    const TAIL_LINES: usize = 20;
+    let lines: Vec<&str> = stderr.lines()
+        .map(|line| line.rsplit('\r').next().unwrap_or_default().trim_end())
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let mut tail = String::new();
+    if lines.len() > TAIL_LINES {
+        tail.push_str(format!("\n  ... ({} earlier lines)", lines.len() - TAIL_LINES).as_str());
+    }
+    for line in lines.iter().skip(lines.len().saturating_sub(TAIL_LINES)) {
+        tail.push_str(format!("\n  {}", line).as_str());
+    }
+    return tail;
+}
+
+/// Why cargit failed, reported as its exit status (1 for anything else)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum FailureKind {
+    /// Invalid arguments
+    Usage,
+    /// A git command failed
+    Git,
+    /// A cargo command failed
+    Cargo,
+    /// Stopped without doing what was asked: a refusal (eg. uncommitted changes or a detatched HEAD) or a 'no' answer
+    Aborted,
+    /// Any other command failed
+    Command
+}
+
+static FAILURE_KIND: OnceLock<FailureKind> = OnceLock::new();
+
+impl FailureKind {
+    fn of_program(program: &str) -> FailureKind {
+        match program {
+            "git" => FailureKind::Git,
+            "cargo" => FailureKind::Cargo,
+            _ => FailureKind::Command
+        }
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            FailureKind::Command => 1,
+            FailureKind::Usage => 2,
+            FailureKind::Git => 3,
+            FailureKind::Cargo => 4,
+            FailureKind::Aborted => 5
+        }
+    }
+
+    /// Name of the kind in JSON error reports
+    pub fn name(&self) -> &'static str {
+        match self {
+            FailureKind::Command => "command",
+            FailureKind::Usage => "usage",
+            FailureKind::Git => "git",
+            FailureKind::Cargo => "cargo",
+            FailureKind::Aborted => "aborted"
+        }
+    }
+}
+
+/// Remembers why cargit is failing. The first failure wins, so commands run to roll back a failed
+/// step cannot hide the failure that caused it
+pub(crate) fn record_failure(kind: FailureKind) {
+    let _ = FAILURE_KIND.set(kind);
+}
+
+pub(crate) fn recorded_failure() -> Option<FailureKind> {
+    return FAILURE_KIND.get().copied();
+}
+
+/// An error in the arguments, exiting with the usage status
+pub(crate) fn usage_error<C>(message: C) -> ErrorChain
+where C: Display + Send + Sync + 'static {
+    record_failure(FailureKind::Usage);
+    return ErrorChain::new(message);
+}
+
+/// Cargit refused to go on or the user answered no, exiting with the aborted status
+pub(crate) fn aborted<C>(message: C) -> ErrorChain
+where C: Display + Send + Sync + 'static {
+    record_failure(FailureKind::Aborted);
+    return ErrorChain::new(message);
+}
+
+/// An external command kept as a value, so it can be printed (eg. for a dry run) before, or instead of, being run
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) struct CliCommand {
+    pub program: String,
+    pub args: Vec<String>
+}
+
+impl CliCommand {
+    pub fn new(program: &str, args: &[&str]) -> CliCommand {
+        return CliCommand { program: program.to_owned(), args: args.iter().map(|arg| arg.to_string()).collect() };
+    }
+
+    /// Splits a command line into program and arguments, honouring single and double quotes
+    pub fn parse(command_line: &str) -> Result<CliCommand, ErrorChain> {
+        let mut words: Vec<String> = Vec::new();
+        let mut current: Option<String> = None;
+        let mut quote: Option<char> = None;
+        let mut chars = command_line.chars();
+        while let Some(c) = chars.next() {
+            match (quote, c) {
+                (Some(open), c) if c == open => quote = None,
+                (Some('"'), '\\') => {
+                    let escaped = chars.next().on_error(format!("unfinished escape in command '{}'", command_line))?;
+                    current.get_or_insert_with(String::new).push(escaped);
+                }
+                (Some(_), c) => current.get_or_insert_with(String::new).push(c),
+                (None, '"') | (None, '\'') => {
+                    quote = Some(c);
+                    current.get_or_insert_with(String::new);
+                }
+                (None, c) if c.is_whitespace() => {
+                    if let Some(word) = current.take() {
+                        words.push(word);
+                    }
+                }
+                (None, c) => current.get_or_insert_with(String::new).push(c)
+            }
+        }
+        if quote.is_some() {
+            return Err(ErrorChain::new(format!("unclosed quote in command '{}'", command_line)));
+        }
+        if let Some(word) = current {
+            words.push(word);
+        }
+        let mut words = words.into_iter();
+        let program = words.next().on_error("empty command")?;
+        return Ok(CliCommand { program, args: words.collect() });
+    }
+
+    pub fn run(&self) -> Result<(), ErrorChain> {
+        return run_cli(self.program.as_str(), &self.args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>());
+    }
+}
+
+impl Display for CliCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in self.args.iter() {
+            if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\' || c == '\'') {
+                write!(f, " \"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))?;
+            } else {
+                write!(f, " {}", arg)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 fn collect_full_command<S>(program: &S, args: &[S]) -> String
 where S: AsRef<str> + AsRef<OsStr> {
     let mut command_string = String::new();
         command_string.push_str(program.as_ref());
-        for arg in args.into_iter() {
+        for arg in args.iter() {
             command_string.push(' ');
             command_string.push_str(arg.as_ref());
         }
@@ -84,59 +635,131 @@ where S: AsRef<OsStr> + AsRef<str> {
     return Ok(cli_output_string);
 }
 
-pub(crate) fn split_version_from_cargo_toml<'a>(cargo_toml_str: &'a str) -> Result<(&'a str, &'a str, &'a str), ErrorChain> {
-    let version_prefix_location = cargo_toml_str.find_first(&VERSION_PREFIX).on_error("could not locate the string 'version = \"' in the Cargo.toml file")?;
-    let version_end_location = cargo_toml_str.find_first_from(&"\"", version_prefix_location.end()).on_error("could not locate a '\"' after the version numbers in the Cargo.toml file")?;
-    let before_version = &cargo_toml_str[..version_prefix_location.end()];
-    let version_string = &cargo_toml_str[version_prefix_location.end()..version_end_location.start()];
-    let after_version = &cargo_toml_str[version_end_location.start()..];
-    return Ok((before_version, version_string, after_version))
-}
-
-pub(crate) fn split_parts_from_version<'a>(version_string: &'a str) -> Result<(&'a str, &'a str, &'a str), ErrorChain> {
+/// Splits a version string into its `major`, `minor`, `patch`, pre-release and build metadata parts
+pub(crate) fn split_parts_from_version(version_string: &str) -> Result<VersionParts<'_>, ErrorChain> {
+    let (version_string, build) = match version_string.find_first(&"+") {
+        Some(build_sep) => (&version_string[..build_sep.start()], Some(&version_string[build_sep.end()..])),
+        None => (version_string, None)
+    };
+    let (version_string, pre) = match version_string.find_first(&"-") {
+        Some(pre_sep) => (&version_string[..pre_sep.start()], Some(&version_string[pre_sep.end()..])),
+        None => (version_string, None)
+    };
     let first_version_period = version_string.find_first(&".").on_error("could not locate a '.' after the major version")?;
     let second_version_period = version_string.find_first_from(&".", first_version_period.end()).on_error("could not locate a '.' after the minor version")?;
     let major = &version_string[..first_version_period.start()];
     let minor = &version_string[first_version_period.end()..second_version_period.start()];
     let patch = &version_string[second_version_period.end()..];
-    return Ok((major, minor, patch));
+    return Ok((major, minor, patch, pre, build));
+}
+
+/// History of HEAD through every parent, so tags on merged branches count
+pub(crate) fn get_reverse_chron_iterator_over_commits_in_current_branch(git: &dyn GitBackend) -> Result<Commiterator<'_>, ErrorChain> {
+    let branch = git.branch_name().on_error("could not get current branch name")?;
+    return get_reverse_chron_iterator_over_commits_in_branch(git, branch.as_str(), Parents::All);
+}
+
+pub(crate) fn get_reverse_chron_iterator_over_commits_in_branch<'a>(git: &'a dyn GitBackend, branch: &str, parents: Parents) -> Result<Commiterator<'a>, ErrorChain> {
+    let tags_by_commit = git.tags_by_commit().on_error("could not retrieve tags")?;
+    let commits = git.commit_history(branch, parents).on_error(format!("could not retrieve the commits of {}", branch))?;
+    return Ok(Commiterator { commits, tags_by_commit })
 }
 
-pub(crate) fn get_reverse_chron_iterator_over_commits_in_current_branch() -> Result<Commiterator, ErrorChain> {
-    let branch = get_branch_name().on_error("could not get current branch name")?;
-    return get_reverse_chron_iterator_over_commits_in_branch(branch.as_str());
+/// The first 12 characters of a commit hash, for messages
+pub(crate) fn short_hash(commit: &str) -> &str {
+    return &commit[..commit.len().min(12)];
 }
 
-pub(crate) fn get_reverse_chron_iterator_over_commits_in_branch(branch: &str) -> Result<Commiterator, ErrorChain> {
-    let all_commits = get_all_commits_in_current_branch().on_error("could not retrieve commits list")?;
-    let mut all_commits_tags: Vec<(String, Vec<String>)> = Vec::new();
-    for commit in all_commits {
-        let tags_on_commit = get_tags_on_commit(&commit).on_error("could_not_retrieve togs on commit")?;
-        all_commits_tags.push((commit, tags_on_commit));
+/// The checked out branch, or the branch HEAD was detatched from
+pub(crate) fn current_or_last_branch(git: &dyn GitBackend) -> Result<String, ErrorChain> {
+    if git.is_detatched_mode().on_error("could not verify head attatchement")? {
+        return git.last_attatched_head_branch().on_error("could not get last branch before detatching");
+    }
+    return git.branch_name().on_error("could not get branch name");
+}
 
+/// The most recent tag in the current branch's history that follows `tag_format`, with its version
+pub(crate) fn find_previous_version_tag(git: &dyn GitBackend, tag_format: &TagFormat) -> Result<Option<(String, Version)>, ErrorChain> {
+    for (_, tags) in get_reverse_chron_iterator_over_commits_in_current_branch(git)? {
+        if let Some((tag, version)) = tag_format.find_version(&tags) {
+            return Ok(Some((tag.to_owned(), version)));
+        }
     }
-    return Ok(Commiterator{ index: 0, commits_and_tags: all_commits_tags })
+    return Ok(None);
 }
 
-pub(crate) fn get_all_commits_in_current_branch() -> Result<Vec<String>, ErrorChain> {
-    let branch = get_branch_name().on_error("could not get current branch name")?;
-    return get_all_commits_in_branch(branch.as_str());
+/// Full messages of the non-merge commits after `since` (or of the whole history) that touch `path`, newest first
+pub(crate) fn get_commit_messages_since(since: Option<&str>, path: &str) -> Result<Vec<String>, ErrorChain> {
+    let range = match since {
+        Some(since) => format!("{}..HEAD", since),
+        None => String::from("HEAD")
+    };
+    let messages = get_cli_output_as_string("git", &["log", range.as_str(), "--no-merges", "--format=%B%x1e", "--", path])?;
+    return Ok(messages.split('\u{1e}').map(|message| message.trim().to_owned()).filter(|message| !message.is_empty()).collect());
 }
 
-pub(crate) fn get_all_commits_in_branch(branch: &str) -> Result<Vec<String>, ErrorChain> {
-    let commits_in_branch = get_cli_output_as_string("git", &["log", branch, "--pretty=format:'%H'"])?;
-    let commits_in_branch_list: Vec<String> = commits_in_branch.split_whitespace().map(|s| s.trim_matches('\'').to_owned()).collect();
-    return Ok(commits_in_branch_list);
+/// Streams the hashes of the commits reachable from `branch`, newest first, from a single `git log`
+pub(crate) fn stream_commits_in_branch(branch: &str, parents: Parents) -> Result<CliLineStream, ErrorChain> {
+    if !git_ref_exists(branch)? {
+        return Err(ErrorChain::new(format!("'{}' is not a branch, tag or commit", branch)));
+    }
+    let order = match parents {
+        Parents::First => "--first-parent",
+        Parents::All => "--date-order"
+    };
+    return CliLineStream::spawn("git", &["log", branch, order, "--format=%H"]);
 }
 
-pub(crate) fn get_tags_on_commit(commit: &String) -> Result<Vec<String>, ErrorChain> {
-    let git_tags_on_commit = get_cli_output_as_string("git", &["tag", "--points-at", commit.as_str()])?;
-    let git_tags_on_commit_list: Vec<String> = git_tags_on_commit.split_whitespace().map(|s| s.to_owned()).collect();
-    return Ok(git_tags_on_commit_list);
+/// The newest commit of `branch` (through `parents`) with a committer date at or before `time`, with that date
+pub(crate) fn get_last_commit_before(branch: &str, parents: Parents, time: i64) -> Result<Option<(String, i64)>, ErrorChain> {
+    if !git_ref_exists(branch)? {
+        return Err(ErrorChain::new(format!("'{}' is not a branch, tag or commit", branch)));
+    }
+    let order = match parents {
+        Parents::First => "--first-parent",
+        Parents::All => "--date-order"
+    };
+    let before = format!("--before=@{}", time);
+    let commit = get_cli_output_as_string("git", &["log", "-1", order, before.as_str(), "--format=%H %ct", branch])?;
+    let (hash, commit_time) = match commit.trim().split_once(' ') {
+        Some(commit) => commit,
+        None => return Ok(None)
+    };
+    let commit_time = commit_time.parse().on_error(format!("could not parse the commit time of {}", hash))?;
+    return Ok(Some((hash.to_owned(), commit_time)));
+}
+
+/// Every tag, by the hash of the commit it points to (annotated tags are peeled), from a single `git for-each-ref`
+pub(crate) fn get_tags_by_commit() -> Result<HashMap<String, Vec<String>>, ErrorChain> {
+    let tag_refs = get_cli_output_as_string("git", &["for-each-ref", "--format=%(objectname)%09%(*objectname)%09%(refname:strip=2)", "refs/tags"])?;
+    let mut tags_by_commit: HashMap<String, Vec<String>> = HashMap::new();
+    for line in tag_refs.lines() {
+        let mut fields = line.splitn(3, '\t');
+        let (object, peeled, tag) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(object), Some(peeled), Some(tag)) => (object, peeled, tag),
+            _ => continue
+        };
+        let commit = if peeled.is_empty() { object } else { peeled };
+        tags_by_commit.entry(commit.to_owned()).or_default().push(tag.to_owned());
+    }
+    return Ok(tags_by_commit);
+}
+
+/// Whether `id` names anything git can check out (a commit hash, tag or branch)
+pub(crate) fn git_ref_exists(id: &str) -> Result<bool, ErrorChain> {
+    let commit_ref = format!("{}^{{commit}}", id);
+    let rev_parse_output = get_cli_output("git", &["rev-parse", "-q", "--verify", commit_ref.as_str()])?;
+    return Ok(rev_parse_output.status.success());
+}
+
+pub(crate) fn git_tag_exists(tag: &str) -> Result<bool, ErrorChain> {
+    let tag_ref = format!("refs/tags/{}", tag);
+    let rev_parse_output = get_cli_output("git", &["rev-parse", "-q", "--verify", tag_ref.as_str()])?;
+    return Ok(rev_parse_output.status.success());
 }
 
 pub(crate) fn get_current_commit() -> Result<String, ErrorChain> {
-    let git_show_output_string = get_cli_output_as_string("git", &["show", "-s", "--format=%H"])?;
+    let git_show_output_string = get_cli_output_as_string("git", &["show", "-s", "--format=%H"])?.trim_end().to_owned();
     return Ok(git_show_output_string);
 }
 
@@ -145,25 +768,92 @@ pub(crate) fn get_branch_name() -> Result<String, ErrorChain> {
     return Ok(branch_name);
 }
 
-pub(crate) fn get_remote_name() -> Result<Option<String>, ErrorChain> {
-    let remote_name = get_cli_output_as_string("git", &["remote"])?.trim().to_owned();
-    if remote_name.is_empty() {
-        return Ok(None);
+pub(crate) fn get_work_dir() -> Result<PathBuf, ErrorChain> {
+    let work_dir = get_cli_output_as_string("git", &["rev-parse", "--show-toplevel"])?;
+    return Ok(PathBuf::from(work_dir.trim_end()));
+}
+
+pub(crate) fn get_common_dir() -> Result<PathBuf, ErrorChain> {
+    let common_dir = get_cli_output_as_string("git", &["rev-parse", "--path-format=absolute", "--git-common-dir"])?;
+    return Ok(PathBuf::from(common_dir.trim_end()));
+}
+
+/// Paths of the linked worktrees, from `git worktree list --porcelain` (whose first entry is the main working tree)
+pub(crate) fn get_linked_worktrees() -> Result<Vec<PathBuf>, ErrorChain> {
+    let worktree_list = get_cli_output_as_string("git", &["worktree", "list", "--porcelain"])?;
+    return Ok(worktree_list.lines().filter_map(|line| line.strip_prefix("worktree ")).skip(1).map(PathBuf::from).collect());
+}
+
+pub(crate) fn get_remote_names() -> Result<Vec<String>, ErrorChain> {
+    let remote_names = get_cli_output_as_string("git", &["remote"])?;
+    return Ok(remote_names.split_whitespace().map(|remote_name| remote_name.to_owned()).collect());
+}
+
+/// Whether anything under `path` differs from `rev`: committed, staged, unstaged or untracked
+pub(crate) fn git_has_changes_since(rev: &str, path: &str) -> Result<bool, ErrorChain> {
+    let changed = get_cli_output_as_string("git", &["diff", "--name-only", rev, "--", path])?;
+    if !changed.trim().is_empty() {
+        return Ok(true);
     }
-    return Ok(Some(remote_name));
+    let untracked = get_cli_output_as_string("git", &["ls-files", "--others", "--exclude-standard", "--", path])?;
+    return Ok(!untracked.trim().is_empty());
+}
+
+pub(crate) fn git_has_uncommitted_changes() -> Result<bool, ErrorChain> {
+    let status = get_cli_output_as_string("git", &["status", "--porcelain", "--untracked-files=no"])?;
+    return Ok(!status.trim().is_empty());
+}
+
+pub(crate) fn get_changed_files() -> Result<Vec<String>, ErrorChain> {
+    let status = get_cli_output_as_string("git", &["status", "--porcelain", "--untracked-files=all"])?;
+    return Ok(status.lines().filter(|line| !line.trim().is_empty()).map(|line| line.to_owned()).collect());
+}
+
+pub(crate) fn git_count_commits(since: &str, until: &str) -> Result<usize, ErrorChain> {
+    let range = format!("{}..{}", since, until);
+    let count = get_cli_output_as_string("git", &["rev-list", "--count", range.as_str()])?;
+    return count.trim().parse().on_error(format!("could not count the commits in {}", range));
+}
+
+pub(crate) fn get_commit_summary(commit: &str) -> Result<(String, i64, String), ErrorChain> {
+    let summary = get_cli_output_as_string("git", &["show", "--no-patch", "--format=%an%x09%ct%x09%s", commit])?;
+    let mut fields = summary.trim_end_matches('\n').splitn(3, '\t');
+    let (Some(author), Some(time), Some(subject)) = (fields.next(), fields.next(), fields.next()) else {
+        return Err(ErrorChain::new(format!("could not read commit {}", commit)));
+    };
+    let time = time.parse().on_error(format!("could not read the commit time of {}", commit))?;
+    return Ok((author.to_owned(), time, subject.to_owned()));
 }
 
-pub(crate) fn is_detatched_mode() -> Result<bool, ErrorChain> {
-    let branch_name = get_branch_name()?;
-    return Ok(branch_name == String::from("HEAD")); // branches CANNOT be named HEAD, HEAD is only returned from get_branch_mode() when in detatched state
+pub(crate) fn get_stashes() -> Result<Vec<(String, String)>, ErrorChain> {
+    let stash_list = get_cli_output_as_string("git", &["stash", "list", "--format=%gd%x09%gs"])?;
+    return Ok(stash_list.lines().filter_map(|line| line.split_once('\t')).map(|(stash, message)| (stash.to_owned(), message.to_owned())).collect());
 }
 
 pub(crate) fn last_attatched_head_branch() -> Result<String, ErrorChain> {
-    let detatch_log = get_cli_output_as_string("git", &["log", "--walk-reflogs", "--grep-reflog", "checkout", "-1", "--oneline"])?;
-    let original_branch_start = detatch_log.find_first(&"checkout: moving from ").on_error("could not locate where HEAD was detatched from branch")?;
-    let original_branch_end = detatch_log.find_first_from(&" to ", original_branch_start.end()).on_error("could not locate where HEAD was detatched from branch")?;
-    let original_branch = detatch_log[original_branch_start.end()..original_branch_end.start()].to_owned();
-    return Ok(original_branch);
+    let checkout_log = get_cli_output_as_string("git", &["log", "--walk-reflogs", "--grep-reflog=checkout: moving from ", "--format=%gs"])?;
+    let local_branches = get_all_local_branches_in_repo()?;
+    return find_detatched_from_branch(checkout_log.lines(), |name| local_branches.iter().any(|branch| branch == name))
+        .on_error("could not locate where HEAD was detatched from branch");
+}
+
+/// The branch of the most recent `checkout: moving from <branch> to ...` reflog message (newest first)
+/// that still names a local branch. Checkouts between detatched commits (eg. repeated `load prev`)
+/// move from a hash, so they are skipped
+pub(crate) fn find_detatched_from_branch<'a, I, F>(reflog_messages: I, is_local_branch: F) -> Option<String>
+where I: Iterator<Item = &'a str>, F: Fn(&str) -> bool {
+    for message in reflog_messages {
+        let moved = match message.trim().strip_prefix("checkout: moving from ") {
+            Some(moved) => moved,
+            None => continue
+        };
+        if let Some((original_branch, _)) = moved.split_once(" to ") {
+            if is_local_branch(original_branch) {
+                return Some(original_branch.to_owned());
+            }
+        }
+    }
+    return None;
 }
 
 pub(crate) fn get_all_local_branches_in_repo() -> Result<Vec<String>, ErrorChain> {
@@ -172,45 +862,123 @@ pub(crate) fn get_all_local_branches_in_repo() -> Result<Vec<String>, ErrorChain
     return Ok(branches);
 }
 
-pub(crate) fn git_checkout(identifier: &str) -> Result<(), ErrorChain> {
-    return run_cli("git", &["checkout", identifier]);
+pub(crate) fn git_checkout(identifier: &str) -> CliCommand {
+    return CliCommand::new("git", &["checkout", identifier]);
+}
+
+pub(crate) fn git_branch(branch_name: &str) -> CliCommand {
+    return CliCommand::new("git", &["branch", branch_name]);
+}
+
+pub(crate) fn git_branch_at(branch_name: &str, start_point: &str) -> CliCommand {
+    return CliCommand::new("git", &["branch", branch_name, start_point]);
+}
+
+pub(crate) fn cargo_generate_lockfile() -> CliCommand {
+    return CliCommand::new("cargo", &["generate-lockfile"]);
+}
+
+pub(crate) fn cargo_publish() -> CliCommand {
+    return CliCommand::new("cargo", &["publish"]);
+}
+
+pub(crate) fn cargo_publish_package(package: &str) -> CliCommand {
+    return CliCommand::new("cargo", &["publish", "-p", package]);
+}
+
+pub(crate) fn git_stage_all_changes() -> CliCommand {
+    return CliCommand::new("git", &["add", "--all"]);
+}
+
+pub(crate) fn git_commit_with_message(message: &str) -> CliCommand {
+    return CliCommand::new("git", &["commit", "-m", message]);
+}
+
+pub(crate) fn git_create_tag(tag: &str) -> CliCommand {
+    return CliCommand::new("git", &["tag", tag]);
+}
+
+/// Creates an annotated tag, signed with the user's configured GPG or SSH key when `sign` is set
+pub(crate) fn git_create_annotated_tag(tag: &str, message: &str, sign: bool) -> CliCommand {
+    let annotate_arg = if sign { "--sign" } else { "--annotate" };
+    return CliCommand::new("git", &["tag", annotate_arg, tag, "-m", message]);
+}
+
+pub(crate) fn git_delete_tag(tag: &str) -> CliCommand {
+    return CliCommand::new("git", &["tag", "--delete", tag]);
+}
+
+pub(crate) fn git_unstage_all_changes() -> CliCommand {
+    return CliCommand::new("git", &["reset", "--quiet"]);
+}
+
+pub(crate) fn git_reset_last_commit(mode: ResetMode) -> CliCommand {
+    let mode_arg = match mode {
+        ResetMode::Soft => "--soft",
+        ResetMode::Hard => "--hard"
+    };
+    return CliCommand::new("git", &["reset", "--quiet", mode_arg, "HEAD~1"]);
+}
+
+pub(crate) fn git_push(remote: &str, branch: &str) -> CliCommand {
+    return CliCommand::new("git", &["push", remote, branch, "--tags"]);
 }
 
-pub(crate) fn git_branch(branch_name: &str) -> Result<(), ErrorChain> {
-    return run_cli("git", &["branch", branch_name]);
+/// Merges `from_branch` into the checked out branch, with the default merge commit message
+pub(crate) fn git_merge(from_branch: &str) -> CliCommand {
+    return CliCommand::new("git", &["merge", "--no-edit", from_branch]);
 }
 
-pub(crate) fn cargo_generate_lockfile() -> Result<(), ErrorChain> {
-    return run_cli("cargo", &["generate-lockfile"]);
+/// Stages the changes of `from_branch` as one change, to be committed separately
+pub(crate) fn git_merge_squash(from_branch: &str) -> CliCommand {
+    return CliCommand::new("git", &["merge", "--squash", from_branch]);
 }
 
-pub(crate) fn cargo_publish() -> Result<(), ErrorChain> {
-    return run_cli("cargo", &["publish"]);
+/// Moves the checked out branch forward to `from_branch`, failing if they have diverged
+pub(crate) fn git_merge_fast_forward(from_branch: &str) -> CliCommand {
+    return CliCommand::new("git", &["merge", "--ff-only", from_branch]);
 }
 
-pub(crate) fn git_stage_all_changes() -> Result<(), ErrorChain> {
-    return run_cli("git", &["add", "."]);
+/// Checks out `branch` and replays its commits on top of `onto`
+pub(crate) fn git_rebase(onto: &str, branch: &str) -> CliCommand {
+    return CliCommand::new("git", &["rebase", onto, branch]);
 }
 
-pub(crate) fn git_commit_with_message(message: &String) -> Result<(), ErrorChain> {
-    return run_cli("git", &["commit", "-m", message.as_str()]);
+/// Commits the staged changes with the message git prepared, eg. the list of squashed commits
+pub(crate) fn git_commit_prepared_message() -> CliCommand {
+    return CliCommand::new("git", &["commit", "--no-edit"]);
 }
 
-pub(crate) fn git_create_tag(tag: &String) -> Result<(), ErrorChain> {
-    return run_cli("git", &["tag", tag.as_str()]);
+pub(crate) fn git_stash_push(message: &str) -> CliCommand {
+    return CliCommand::new("git", &["stash", "push", "--message", message]);
 }
 
-pub(crate) fn git_push(remote: &String, branch: &String) -> Result<(), ErrorChain> {
-    return run_cli("git", &["push", remote.as_str(), branch.as_str(), "--tags"]);
+pub(crate) fn git_stash_pop(stash: &str) -> CliCommand {
+    return CliCommand::new("git", &["stash", "pop", stash]);
 }
 
-pub(crate) fn git_merge(from_branch: &String, into_branch: &String) -> Result<(), ErrorChain> {
-    git_checkout(&into_branch)?;
-    return run_cli("git", &["merge", from_branch.as_str()]);
+/// Checks `id` out in a new worktree at `path`, with a detatched HEAD so a branch can be checked out twice
+pub(crate) fn git_worktree_add(path: &Path, id: &str) -> CliCommand {
+    return CliCommand::new("git", &["worktree", "add", "--detach", path.to_string_lossy().as_ref(), id]);
 }
 
-pub(crate) fn git_delete_branch(branch: &String) -> Result<(), ErrorChain> {
-    return run_cli("git", &["branch", "--delete", branch.as_str()]);
+pub(crate) fn git_worktree_remove(path: &Path) -> CliCommand {
+    return CliCommand::new("git", &["worktree", "remove", path.to_string_lossy().as_ref()]);
+}
+
+pub(crate) fn git_worktree_prune() -> CliCommand {
+    return CliCommand::new("git", &["worktree", "prune"]);
+}
+
+pub(crate) fn cargo_build_manifest(manifest_path: &Path) -> CliCommand {
+    return CliCommand::new("cargo", &["build", "--manifest-path", manifest_path.to_string_lossy().as_ref()]);
+}
+
+/// Deletes a local branch. Without `force` git refuses when it is not merged into the checked out branch,
+/// which is always the case after a squash merge
+pub(crate) fn git_delete_branch(branch: &str, force: bool) -> CliCommand {
+    let delete_arg = if force { "-D" } else { "--delete" };
+    return CliCommand::new("git", &["branch", delete_arg, branch]);
 }
 
 // pub(crate) git_push_branch()
@@ -240,16 +1008,112 @@ mod tests {
         }
     }
 
+    fn version(version_string: &str) -> Version {
+        unwrap_or_panic(Version::parse(version_string))
+    }
+
     #[test]
-    #[ignore]
-    fn test_get_all_commits_in_current_branch() {
-        print_or_panic(get_all_commits_in_current_branch());
+    fn test_version_parse_and_display() {
+        for version_string in ["0.4.0", "0.4.0-beta.1", "1.0.0+build.5", "1.0.0-rc.3+exp.sha.5114f85", "1.0.0-x-y-z.--"] {
+            assert_eq!(version(version_string).to_string(), version_string);
+        }
+        let parsed = version("1.2.3-beta.11+build.5");
+        assert_eq!((parsed.major, parsed.minor, parsed.patch), (1, 2, 3));
+        assert_eq!(parsed.pre, vec![PreReleaseIdentifier::AlphaNumeric("beta".to_owned()), PreReleaseIdentifier::Numeric(11)]);
+        assert_eq!(parsed.build, vec!["build".to_owned(), "5".to_owned()]);
+        for invalid in ["1.2", "01.2.3", "1.2.3-", "1.2.3-beta..1", "1.2.3-01", "1.2.3+", "1.2.3-beta_1"] {
+            assert!(Version::parse(invalid).is_err(), "{} should not parse", invalid);
+        }
+    }
+
+    #[test]
+    fn test_version_precedence() {
+        let ordered = ["1.0.0-alpha", "1.0.0-alpha.1", "1.0.0-alpha.beta", "1.0.0-beta", "1.0.0-beta.2", "1.0.0-beta.11", "1.0.0-rc.1", "1.0.0", "1.0.1", "1.1.0", "2.0.0"];
+        for pair in ordered.windows(2) {
+            assert!(version(pair[0]) < version(pair[1]), "{} should precede {}", pair[0], pair[1]);
+        }
+        assert_eq!(version("1.0.0+a").cmp(&version("1.0.0+a")), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_version_bumps() {
+        assert_eq!(version("1.2.3").increment(&VersionPart::Major).to_string(), "2.0.0");
+        assert_eq!(version("1.2.3").increment(&VersionPart::Minor).to_string(), "1.3.0");
+        assert_eq!(version("1.2.3+build.1").increment(&VersionPart::Patch).to_string(), "1.2.4");
+        assert_eq!(version("2.0.0-rc.1").increment(&VersionPart::Major).to_string(), "2.0.0");
+        assert_eq!(version("1.2.4-beta.1").increment(&VersionPart::Minor).to_string(), "1.3.0");
+        assert_eq!(version("1.2.3").next_pre_release(None, "alpha").to_string(), "1.2.4-alpha.1");
+        assert_eq!(version("1.2.3").next_pre_release(Some(&VersionPart::Major), "rc").to_string(), "2.0.0-rc.1");
+        assert_eq!(version("1.0.0-alpha.1").next_pre_release(None, "alpha").to_string(), "1.0.0-alpha.2");
+        assert_eq!(version("1.0.0-alpha").next_pre_release(None, "alpha").to_string(), "1.0.0-alpha.1");
+        assert_eq!(version("1.0.0-beta.2").next_pre_release(None, "rc").to_string(), "1.0.0-rc.1");
+        assert_eq!(version("1.0.0-rc.3+build.5").release().to_string(), "1.0.0");
+        assert_eq!(version("1.3.0").bumped_part(&version("1.2.3")), VersionPart::Minor);
+        assert_eq!(version("2.0.0-alpha.1").bumped_part(&version("1.2.3")), VersionPart::Major);
+        assert_eq!(VersionPart::Major.for_version(&version("0.4.1")), VersionPart::Minor);
+        assert_eq!(VersionPart::Minor.for_version(&version("0.4.1")), VersionPart::Patch);
+        assert_eq!(VersionPart::Major.for_version(&version("0.0.4")), VersionPart::Patch);
+        assert_eq!(VersionPart::Minor.for_version(&version("1.0.0")), VersionPart::Minor);
+    }
+
+    #[test]
+    fn test_tag_format() {
+        let prefixed = TagFormat::new("v{version}", "foo");
+        assert_eq!(prefixed.render(&version("1.2.3")), "v1.2.3");
+        assert_eq!(prefixed.parse("v1.2.3-rc.1"), Some(version("1.2.3-rc.1")));
+        assert_eq!(prefixed.parse("1.2.3"), None);
+        let per_crate = TagFormat::new(DEFAULT_WORKSPACE_TAG_TEMPLATE, "foo");
+        assert_eq!(per_crate.parse("foo-v0.4.0"), Some(version("0.4.0")));
+        assert_eq!(per_crate.parse("bar-v0.4.0"), None);
+        let tags = vec!["foo-v0.4.0".to_owned(), "foo-v0.10.0".to_owned(), "latest".to_owned()];
+        assert_eq!(per_crate.find_version(&tags).map(|(tag, _)| tag), Some("foo-v0.10.0"));
+    }
+
+    #[test]
+    fn test_commiterator_over_fake_history() {
+        let git = FakeGit::new("main", &[("c3", &["v1.1.0", "stable"], "feat: three"), ("c2", &[], "fix: two"), ("c1", &["v1.0.0"], "feat: one")]);
+        let history: Vec<(String, Vec<String>)> = get_reverse_chron_iterator_over_commits_in_current_branch(&git).ok().unwrap().collect();
+        assert_eq!(history, vec![
+            (String::from("c3"), vec![String::from("v1.1.0"), String::from("stable")]),
+            (String::from("c2"), vec![]),
+            (String::from("c1"), vec![String::from("v1.0.0")]),
+        ]);
+        let previous = unwrap_or_panic(find_previous_version_tag(&git, &TagFormat::new("v{version}", "foo")));
+        assert_eq!(previous, Some((String::from("v1.1.0"), version("1.1.0"))));
+    }
+
+    #[test]
+    fn test_find_detatched_from_branch() {
+        let reflog = ["checkout: moving from 1a2b3c to 4d5e6f", "commit: fix", "checkout: moving from feature to 1a2b3c", "checkout: moving from main to feature"];
+        let is_local_branch = |name: &str| name == "main" || name == "feature";
+        assert_eq!(find_detatched_from_branch(reflog.into_iter(), is_local_branch), Some(String::from("feature")));
+        assert_eq!(find_detatched_from_branch(reflog.into_iter(), |name| name == "main"), Some(String::from("main")));
+        assert_eq!(find_detatched_from_branch(reflog[..2].iter().copied(), is_local_branch), None);
+    }
+
+    #[test]
+    fn test_run_cli_reports_stderr() {
+        let err = run_cli("git", &["--cargit-invalid-option"]).unwrap_err().to_string();
+        assert!(err.starts_with("command exited with abnormal status (exit code 129)"), "{}", err);
+        assert!(err.contains("\n  unknown option: --cargit-invalid-option"), "{}", err);
+        assert_eq!(stderr_tail("Compiling a\rCompiling b\n\nerror: x\n"), "\n  Compiling b\n  error: x");
+    }
+
+    #[test]
+    fn test_cli_command_parse() {
+        let command = unwrap_or_panic(CliCommand::parse("cargo clippy  --all-targets -- -D warnings"));
+        assert_eq!(command, CliCommand::new("cargo", &["clippy", "--all-targets", "--", "-D", "warnings"]));
+        let quoted = unwrap_or_panic(CliCommand::parse(r#"sh -c 'echo "hi there"' "" "a \"b\"""#));
+        assert_eq!(quoted, CliCommand::new("sh", &["-c", "echo \"hi there\"", "", "a \"b\""]));
+        assert_eq!(CliCommand::parse(&quoted.to_string()).ok(), Some(quoted));
+        assert!(CliCommand::parse("echo 'oops").is_err());
+        assert!(CliCommand::parse("   ").is_err());
     }
 
     #[test]
     #[ignore]
-    fn test_get_tags_on_commit() {
-        print_or_panic(get_tags_on_commit(&String::from("308ce2999e9d4e53143a0821f23821b199a1f444")));
+    fn test_get_all_commits_in_current_branch() {
+        print_or_panic(stream_commits_in_branch(&unwrap_or_panic(get_branch_name()), Parents::First).map(|commits| commits.collect::<Vec<String>>()));
     }
 
     #[test]
@@ -267,13 +1131,13 @@ mod tests {
     #[test]
     #[ignore]
     fn test_get_remote_name() {
-        print_or_panic(get_remote_name());
+        print_or_panic(CliGit.remote_name());
     }
 
     #[test]
     #[ignore]
     fn test_is_detatched_mode() {
-        print_or_panic(is_detatched_mode());
+        print_or_panic(CliGit.is_detatched_mode());
     }
 
     #[test]
@@ -291,7 +1155,7 @@ mod tests {
     #[test]
     #[ignore]
     fn test_get_detatched_or_attatched_branch() {
-        let branch_result = if unwrap_or_panic(is_detatched_mode()) {
+        let branch_result = if unwrap_or_panic(CliGit.is_detatched_mode()) {
             unwrap_or_panic(last_attatched_head_branch())
         } else {
             unwrap_or_panic(get_branch_name())
@@ -302,7 +1166,7 @@ mod tests {
     #[test]
     #[ignore]
     fn test_iterator_over_commits_and_tags() {
-        let commiterator_result = get_reverse_chron_iterator_over_commits_in_current_branch();
+        let commiterator_result = get_reverse_chron_iterator_over_commits_in_current_branch(&CliGit);
         match commiterator_result {
             Ok(commiterator) => {
                 let mut output = String::new();
@@ -1,93 +1,491 @@
 use gmec::types::error_chain::ErrorChain;
 use gmec::types::error_chain::ErrorPropogation;
 
+use crate::config::*;
+use crate::date::*;
+use crate::git::*;
 use crate::internal::*;
+use crate::plan::*;
+use crate::report::*;
+use crate::version_req::*;
+use crate::workspace::*;
+use crate::worktree::*;
 
 
 const LOAD_PREV: &str = "prev";
 const LOAD_NEXT: &str = "next";
+const LOAD_PREV_RELEASE: &str = "prev-release";
+const LOAD_NEXT_RELEASE: &str = "next-release";
 const LOAD_LATEST: &str = "latest";
+const LOAD_DRY_RUN: &str = "--dry-run";
+const LOAD_ALL_PARENTS: &str = "--all-parents";
+const LOAD_AT: &str = "--at";
+const LOAD_AGO: &str = "--ago";
+const LOAD_STASH: &str = "--stash";
+const LOAD_WORKTREE: &str = "--worktree";
+const LOAD_BUILD: &str = "--build";
+const LOAD_LIST_WORKTREES: &str = "--list-worktrees";
+const LOAD_PRUNE: &str = "--prune";
 
+struct LoadModeOptions {
+    load_mode: LoadMode,
+    load_mode_set: bool,
+    dry_run: bool,
+    /// Stash uncommitted changes, and re-apply them on `load latest`, without asking
+    stash: bool,
+    /// Check out into a new worktree, at the given path or the default one
+    worktree: Option<Option<String>>,
+    build: bool,
+    list_worktrees: bool,
+    prune: bool,
+    parents: Parents
+}
+
+impl LoadModeOptions {
+    fn blank() -> LoadModeOptions {
+        return LoadModeOptions {
+            load_mode: LoadMode::Latest,
+            load_mode_set: false,
+            dry_run: false,
+            stash: false,
+            worktree: None,
+            build: false,
+            list_worktrees: false,
+            prune: false,
+            parents: Parents::First
+        };
+    }
+}
+
+/// The steps modes carry how many commits (or releases) to move
 enum LoadMode {
-    Prev,
-    Next,
+    Prev(usize),
+    Next(usize),
+    PrevRelease(usize),
+    NextRelease(usize),
     Latest,
+    /// The last commit at or before a Unix timestamp
+    At(i64),
     ID(String),
 }
 
-pub(crate) fn load_process<I>(mut args_iter: I) -> Result<String, ErrorChain>
+pub(crate) fn load_process<I>(args_iter: I) -> Result<Report, ErrorChain>
 where I: Iterator<Item = String> {
-    let mutliple_load_mode_set_error = format!("load mode ('{}' or '{}' or '{}' or '<hash>' or '<tag>' or '<branch>') set more than once", LOAD_PREV, LOAD_NEXT, LOAD_LATEST);
-    let mut load_mode: LoadMode = LoadMode::Latest;
-    let mut load_mode_set = false;
+    let LoadModeOptions { load_mode, dry_run, stash, worktree, build, list_worktrees, prune, parents, .. } = process_args(args_iter).inspect_err(|_| record_failure(FailureKind::Usage))?;
+    let git = open_git_backend()?;
+    let git = git.as_ref();
+    if list_worktrees || prune {
+        let mut messages = Vec::new();
+        let mut report = Report::new(String::new());
+        if list_worktrees {
+            let (message, worktrees) = list_load_worktrees(git)?;
+            messages.push(message);
+            report = report.field("worktrees", worktrees);
+        }
+        if prune {
+            let (message, pruned) = prune_load_worktrees(git, dry_run)?;
+            messages.push(message);
+            report = report.field("dry_run", Json::Bool(dry_run)).field("pruned", pruned);
+        }
+        return Ok(report.with_message(messages.join("\n")));
+    }
+    let tag_format = current_tag_format(load_config()?.tag_template.as_deref())?;
+    let returning_to_tip = matches!(load_mode, LoadMode::Latest);
+    let previous_commit = git.current_commit()?;
+    let (id_to_checkout, mut load_output, checkout_error) = choose_checkout(git, load_mode, parents, &tag_format)?;
+    let report = Report::new(String::new())
+        .field("dry_run", Json::Bool(dry_run))
+        .field("previous_commit", Json::str(&previous_commit))
+        .field("ref", Json::str(&id_to_checkout));
+    if let Some(path) = worktree {
+        let (plan, path) = plan_load_worktree(git, &id_to_checkout, path.as_deref(), build)?;
+        load_output.push_str(format!("\nin the new worktree {}, the current checkout is unchanged", path.display()).as_str());
+        let report = report.field("worktree", Json::str(path.display()));
+        if dry_run {
+            let report = report.field("steps_planned", Json::strs(plan.summaries()));
+            return Ok(report.with_message(format!("Dry run, nothing was changed! Would have {}\n{}", load_output, plan)));
+        }
+        plan.execute().on_error(format!("{} (the worktree was kept if it was created, 'cargit load {}' removes it)", checkout_error, LOAD_PRUNE))?;
+        return Ok(report.field("steps_done", Json::strs(plan.summaries())).with_message(load_output));
+    }
+    let branch = current_or_last_branch(git)?;
+    let stash_label = load_stash_label(&branch);
+    let mut plan = Plan::transactional();
+    let stashing = git.has_uncommitted_changes().on_error("could not check the working tree for uncommitted changes")?;
+    if stashing {
+        if !dry_run && !stash && !confirm(&format!("The working tree has uncommitted changes.\nStash them as '{}' and continue? (y/n): ", stash_label))? {
+            return Err(aborted(format!("Load aborted, the working tree has uncommitted changes. Commit them, or use '{}' to stash them", LOAD_STASH)));
+        }
+        plan.run_with_undo(git_stash_push(&stash_label), Undo::Run(git_stash_pop("stash@{0}")));
+        load_output.push_str(format!("\nstashed the uncommitted changes as '{}', 'cargit load {}' offers to re-apply them", stash_label, LOAD_LATEST).as_str());
+    }
+    plan.run(git_checkout(&id_to_checkout));
+    let mut reapplied = None;
+    if returning_to_tip && !stashing {
+        if let Some(stashed) = find_load_stash(&git.stashes()?, &stash_label) {
+            if dry_run || stash || confirm(&format!("Found changes stashed when loading away from {} ({}).\nRe-apply them now? (y/n): ", branch, stashed))? {
+                plan.run(git_stash_pop(&stashed));
+                load_output.push_str(format!("\nre-applied the changes stashed as '{}' ({})", stash_label, stashed).as_str());
+                reapplied = Some(stashed);
+            }
+        }
+    }
+    let report = report
+        .field("stashed", Json::opt_str(Some(&stash_label).filter(|_| stashing)))
+        .field("reapplied", Json::opt_str(reapplied));
+    if dry_run {
+        let report = report.field("steps_planned", Json::strs(plan.summaries()));
+        return Ok(report.with_message(format!("Dry run, nothing was changed! Would have {}\n{}", load_output, plan)));
+    }
+    plan.execute().on_error(checkout_error)?;
+    let checked_out_branch = git.branch_name()?;
+    let report = report
+        .field("commit", Json::str(git.current_commit()?))
+        .field("branch", Json::opt_str(Some(checked_out_branch).filter(|branch| branch != "HEAD")))
+        .field("steps_done", Json::strs(plan.summaries()));
+    return Ok(report.with_message(load_output))
+}
+
+fn process_args<I>(args_iter: I) -> Result<LoadModeOptions, ErrorChain>
+where I: Iterator<Item = String> {
+    let mutliple_load_mode_set_error = format!("load mode ('{}' or '{}' or '{}' or '{}' or '{}' or '<hash>' or '<tag>' or '<branch>' or '<version>' or '{}' or '{}') set more than once", LOAD_PREV, LOAD_NEXT, LOAD_PREV_RELEASE, LOAD_NEXT_RELEASE, LOAD_LATEST, LOAD_AT, LOAD_AGO);
+    let mut options = LoadModeOptions::blank();
+    let mut args_iter = args_iter.peekable();
     while let Some(next_arg) = args_iter.next() {
         let next_arg_lower = next_arg.to_lowercase();
         match next_arg_lower.as_str() {
-            LOAD_PREV | LOAD_NEXT | LOAD_LATEST => {
-                if load_mode_set {
+            LOAD_DRY_RUN => {
+                options.dry_run = true;
+            }
+            LOAD_ALL_PARENTS => {
+                options.parents = Parents::All;
+            }
+            LOAD_STASH => {
+                options.stash = true;
+            }
+            LOAD_WORKTREE => {
+                options.worktree = Some(None);
+            }
+            // The path is only taken in the `--worktree=<path>` form, so `--worktree 1.4.2` still loads 1.4.2
+            _ if next_arg_lower.starts_with(&format!("{}=", LOAD_WORKTREE)) => {
+                let path = &next_arg[LOAD_WORKTREE.len() + 1..];
+                if path.is_empty() {
+                    return Err(ErrorChain::new(format!("'{}=' must be followed by the path of the new worktree", LOAD_WORKTREE)));
+                }
+                options.worktree = Some(Some(path.to_owned()));
+            }
+            LOAD_BUILD => {
+                options.build = true;
+            }
+            LOAD_LIST_WORKTREES => {
+                options.list_worktrees = true;
+            }
+            LOAD_PRUNE => {
+                options.prune = true;
+            }
+            LOAD_AT | LOAD_AGO => {
+                if options.load_mode_set {
                     return Err(ErrorChain::new(mutliple_load_mode_set_error));
                 }
-                load_mode = match next_arg_lower.as_str() {
-                    LOAD_PREV => LoadMode::Prev,
-                    LOAD_NEXT => LoadMode::Next,
-                    LOAD_LATEST => LoadMode::Latest,
-                    _ => LoadMode::Latest // Impossible
+                let time = match next_arg_lower.as_str() {
+                    LOAD_AT => parse_date_time(&args_iter.next().on_error(format!("the '{}' option must be followed by a date, eg. '2026-03-01'", LOAD_AT))?)?,
+                    _ => now() - parse_duration(&args_iter.next().on_error(format!("the '{}' option must be followed by a duration, eg. '2w'", LOAD_AGO))?)?
                 };
-                load_mode_set = true;
+                options.load_mode = LoadMode::At(time);
+                options.load_mode_set = true;
             }
-            id => {
-                if load_mode_set {
+            LOAD_PREV | LOAD_NEXT | LOAD_PREV_RELEASE | LOAD_NEXT_RELEASE | LOAD_LATEST => {
+                if options.load_mode_set {
                     return Err(ErrorChain::new(mutliple_load_mode_set_error));
                 }
-                load_mode = LoadMode::ID(id.to_owned());
-                load_mode_set = true;
+                let mut count = 1;
+                if next_arg_lower != LOAD_LATEST {
+                    if let Some(number) = args_iter.peek().and_then(|number| number.parse::<usize>().ok()) {
+                        if number == 0 {
+                            return Err(ErrorChain::new(format!("the count after '{}' must be at least 1", next_arg_lower)));
+                        }
+                        count = number;
+                        args_iter.next();
+                    }
+                }
+                options.load_mode = match next_arg_lower.as_str() {
+                    LOAD_PREV => LoadMode::Prev(count),
+                    LOAD_NEXT => LoadMode::Next(count),
+                    LOAD_PREV_RELEASE => LoadMode::PrevRelease(count),
+                    LOAD_NEXT_RELEASE => LoadMode::NextRelease(count),
+                    _ => LoadMode::Latest
+                };
+                options.load_mode_set = true;
+            }
+            _ if take_output_format(&next_arg, &mut args_iter)? => {}
+            _ => {
+                if options.load_mode_set {
+                    return Err(ErrorChain::new(mutliple_load_mode_set_error));
+                }
+                options.load_mode = LoadMode::ID(next_arg);
+                options.load_mode_set = true;
             }
         }
     }
-    let current_branch = if is_detatched_mode().on_error("could not verify head attatchement")? {
-        last_attatched_head_branch().on_error("could not get last branch before detatching")?
-    } else {
-        get_branch_name().on_error("could not get branch name")?
+    if options.build && options.worktree.is_none() {
+        return Err(ErrorChain::new(format!("'{}' builds the new worktree, it can only be used with '{}'", LOAD_BUILD, LOAD_WORKTREE)));
+    }
+    if (options.list_worktrees || options.prune) && (options.load_mode_set || options.worktree.is_some()) {
+        return Err(ErrorChain::new(format!("'{}' and '{}' cannot be combined with a load mode or '{}'", LOAD_LIST_WORKTREES, LOAD_PRUNE, LOAD_WORKTREE)));
+    }
+    return Ok(options);
+}
+
+fn confirm(question: &str) -> Result<bool, ErrorChain> {
+    let mut buffer = String::new();
+    print_message(question);
+    read_stdin_line(&mut buffer)?;
+    return Ok(cli_affirmative(buffer));
+}
+
+/// The message of the stash load makes when leaving `branch` with uncommitted changes
+fn load_stash_label(branch: &str) -> String {
+    return format!("cargit load from {}", branch);
+}
+
+/// The newest stash made by load with `label` (git prefixes stash messages with `On <branch>: `)
+fn find_load_stash(stashes: &[(String, String)], label: &str) -> Option<String> {
+    let suffix = format!(": {}", label);
+    return stashes.iter().find(|(_, message)| message.ends_with(&suffix)).map(|(stash, _)| stash.clone());
+}
+
+/// The ref to check out for `load_mode`, with the messages for success and failure. `prev` and `next` step
+/// along the history of the checked out branch, or of the branch HEAD was detatched from, following `parents`
+fn choose_checkout(git: &dyn GitBackend, load_mode: LoadMode, parents: Parents, tag_format: &TagFormat) -> Result<(String, String, String), ErrorChain> {
+    let current_branch = current_or_last_branch(git)?;
+    let current_commit = git.current_commit().on_error("could not get current commit")?;
+    let not_in_history_error = || -> ErrorChain {
+        match parents {
+            Parents::First => ErrorChain::new(format!("the current commit ({}) is not on the first-parent history of branch {}, use '{}' to also step through merged branches", current_commit, current_branch, LOAD_ALL_PARENTS)),
+            Parents::All => ErrorChain::new(format!("the current commit ({}) is not in the history of branch {}", current_commit, current_branch))
+        }
     };
-    let current_commit = get_current_commit().on_error("could not get current commit")?;
-    let commiterator = get_reverse_chron_iterator_over_commits_in_branch(&current_branch).on_error("could not obtain an iterator over all commits in branch")?;
-    let load_output = match load_mode {
-        LoadMode::Prev => {
-            let mut next_commit_is_before_current = false;
-            let mut id_to_checkout: Option<String> = None;
-            for (commit, _) in commiterator {
-                if next_commit_is_before_current {
-                    id_to_checkout = Some(commit);
-                    break;
-                } else if commit == current_commit {
-                    next_commit_is_before_current = true;
-                }
-            }
-            let good_commit = id_to_checkout.on_error("no commit found earlier than current")?;
-            git_checkout(&good_commit).on_error(format!("error checking out commit {}", good_commit))?;
-            format!("checked out previous commit ({})", good_commit)
-        },
-        LoadMode::Next => {
-            let mut id_to_checkout: Option<String> = None;
-            for (commit, _) in commiterator {
+    let checkout = match load_mode {
+        LoadMode::Prev(count) | LoadMode::Next(count) | LoadMode::PrevRelease(count) | LoadMode::NextRelease(count) => {
+            let earlier = matches!(load_mode, LoadMode::Prev(_) | LoadMode::PrevRelease(_));
+            let releases_only = matches!(load_mode, LoadMode::PrevRelease(_) | LoadMode::NextRelease(_));
+            let is_stop = |tags: &[String]| !releases_only || tag_format.find_version(tags).is_some();
+            let mut commiterator = get_reverse_chron_iterator_over_commits_in_branch(git, &current_branch, parents).on_error("could not obtain an iterator over all commits in branch")?;
+            let mut later_stops: Vec<(String, Vec<String>)> = Vec::new();
+            let mut found_current = false;
+            for (commit, tags) in commiterator.by_ref() {
                 if commit == current_commit {
+                    found_current = true;
                     break;
-                } else {
-                    id_to_checkout = Some(commit);
                 }
+                if is_stop(&tags) {
+                    later_stops.push((commit, tags));
+                }
+            }
+            if !found_current {
+                return Err(not_in_history_error());
             }
-            let good_commit = id_to_checkout.on_error("no commit found later than current")?;
-            git_checkout(&good_commit).on_error(format!("error checking out commit {}", good_commit))?;
-            format!("checked out next commit ({})", good_commit)
+            let stop = if earlier {
+                commiterator.filter(|(_, tags)| is_stop(tags)).nth(count - 1)
+            } else {
+                later_stops.len().checked_sub(count).map(|index| later_stops.swap_remove(index))
+            };
+            let unit = if releases_only { "release" } else { "commit" };
+            let (relative, direction) = if earlier { ("earlier", "previous") } else { ("later", "next") };
+            let (good_commit, tags) = match stop {
+                Some(stop) => stop,
+                None if count == 1 => return Err(ErrorChain::new(format!("no {} found {} than current", unit, relative))),
+                None => return Err(ErrorChain::new(format!("fewer than {} {}s found {} than current", count, unit, relative)))
+            };
+            let output = if count == 1 {
+                format!("checked out {} {} ({}){}", direction, unit, good_commit, describe_version_tag(tag_format, &tags))
+            } else {
+                format!("checked out the {} {} {} than current ({}){}", unit, count, relative, good_commit, describe_version_tag(tag_format, &tags))
+            };
+            (good_commit.clone(), output, format!("error checking out commit {}", good_commit))
         },
         LoadMode::Latest => {
-            git_checkout(&current_branch).on_error(format!("error checking out latest commit in branch {}", current_branch))?;
-            format!("checked out latest commit in branch {}", current_branch)
+            (current_branch.clone(), format!("checked out latest commit in branch {}", current_branch), format!("error checking out latest commit in branch {}", current_branch))
+        },
+        LoadMode::At(time) => {
+            let (commit, commit_time) = git.last_commit_before(&current_branch, parents, time)?
+                .on_error(format!("branch {} has no commit at or before {}", current_branch, format_timestamp(time)))?;
+            let output = format!("checked out the last commit of {} at or before {} ({}, committed {}){}", current_branch, format_timestamp(time), commit, format_timestamp(commit_time), describe_version_at(git, tag_format, &commit, parents)?);
+            (commit.clone(), output, format!("error checking out commit {}", commit))
         },
         LoadMode::ID(id) => {
-            git_checkout(&id).on_error(format!("error checking out {}, must be a tag, commit hash, branch name, or one of 'last', 'next', or 'latest'", id))?;
-            format!("checked out {}", id)
+            let (id, output) = resolve_version_tag(git, tag_format, &current_branch, id)?;
+            (id.clone(), output, format!("error checking out {}, must be a tag, commit hash, branch name, version, version requirement, or one of 'prev', 'next', 'prev-release', 'next-release' or 'latest'", id))
         },
     };
-    return Ok(load_output)
-}
\ No newline at end of file
+    return Ok(checkout);
+}
+
+/// Maps an `id` that is not itself a git ref to a version tag: a bare version (`1.2.3`) to its tag (eg. `v1.2.3`),
+/// a partial version or requirement (`1.4`, `^1.2`, `<2.0`) to the tag of the highest matching version in the
+/// history of `branch`. Returns the ref to check out and the message for success
+fn resolve_version_tag(git: &dyn GitBackend, tag_format: &TagFormat, branch: &str, id: String) -> Result<(String, String), ErrorChain> {
+    let checked_out = |id: &str| format!("checked out {}", id);
+    if git.ref_exists(&id)? {
+        return Ok((id.clone(), checked_out(&id)));
+    }
+    if let Ok(version) = Version::parse(&id) {
+        let tag = tag_format.render(&version);
+        if git.tag_exists(&tag)? {
+            return Ok((tag.clone(), checked_out(&tag)));
+        }
+    }
+    let requirement = match VersionReq::parse(&id) {
+        Ok(requirement) => requirement,
+        Err(_) => return Ok((id.clone(), checked_out(&id)))
+    };
+    let mut newest: Option<(String, Version)> = None;
+    for (_, tags) in get_reverse_chron_iterator_over_commits_in_branch(git, branch, Parents::All)? {
+        for tag in tags {
+            let version = match tag_format.parse(&tag) {
+                Some(version) if requirement.matches(&version) => version,
+                _ => continue
+            };
+            if newest.as_ref().is_none_or(|(_, newest)| version > *newest) {
+                newest = Some((tag, version));
+            }
+        }
+    }
+    let (tag, version) = newest.on_error(format!("no version tag in the history of {} matches '{}' (tags look like '{}')", branch, id, tag_format.template.replace("{crate}", &tag_format.crate_name)))?;
+    return Ok((tag.clone(), format!("checked out {}, version {} is the highest matching '{}'", tag, version, id)));
+}
+
+/// The version of `commit`, from its own version tag or the closest earlier one
+fn describe_version_at(git: &dyn GitBackend, tag_format: &TagFormat, commit: &str, parents: Parents) -> Result<String, ErrorChain> {
+    for (distance, (_, tags)) in get_reverse_chron_iterator_over_commits_in_branch(git, commit, parents)?.enumerate() {
+        match (distance, tag_format.find_version(&tags)) {
+            (_, None) => continue,
+            (0, Some((tag, version))) => return Ok(format!(", version {} (tag {})", version, tag)),
+            (1, Some((tag, version))) => return Ok(format!(", 1 commit after version {} (tag {})", version, tag)),
+            (distance, Some((tag, version))) => return Ok(format!(", {} commits after version {} (tag {})", distance, version, tag))
+        }
+    }
+    return Ok(String::new());
+}
+
+fn describe_version_tag(tag_format: &TagFormat, tags: &[String]) -> String {
+    match tag_format.find_version(tags) {
+        Some((tag, version)) => format!(", version {} (tag {})", version, tag),
+        None => String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_history() -> FakeGit {
+        return FakeGit::new("main", &[
+            ("c3", &["v1.1.0"], "feat: three"),
+            ("c2", &[], "fix: two"),
+            ("c1", &["v1.0.0"], "feat: one"),
+        ]);
+    }
+
+    #[test]
+    fn test_choose_checkout() {
+        let tag_format = TagFormat::new("v{version}", "foo");
+        let git = fake_history();
+        let (id, output, _) = choose_checkout(&git, LoadMode::Prev(1), Parents::First, &tag_format).unwrap();
+        assert_eq!((id.as_str(), output.as_str()), ("c2", "checked out previous commit (c2)"));
+        assert!(choose_checkout(&git, LoadMode::Next(1), Parents::First, &tag_format).is_err());
+        let (id, output, _) = choose_checkout(&git, LoadMode::Latest, Parents::First, &tag_format).unwrap();
+        assert_eq!((id.as_str(), output.as_str()), ("main", "checked out latest commit in branch main"));
+        let (id, _, _) = choose_checkout(&git, LoadMode::ID(String::from("1.1.0")), Parents::First, &tag_format).unwrap();
+        assert_eq!(id, "v1.1.0");
+        let (id, _, _) = choose_checkout(&git, LoadMode::ID(String::from("^1.0")), Parents::First, &tag_format).unwrap();
+        assert_eq!(id, "v1.1.0");
+    }
+
+    #[test]
+    fn test_choose_checkout_from_detatched_head() {
+        let tag_format = TagFormat::new("v{version}", "foo");
+        let mut git = fake_history();
+        git.head = 1;
+        git.detatched = true;
+        let (id, output, _) = choose_checkout(&git, LoadMode::Next(1), Parents::First, &tag_format).unwrap();
+        assert_eq!((id.as_str(), output.as_str()), ("c3", "checked out next commit (c3), version 1.1.0 (tag v1.1.0)"));
+        let (id, output, _) = choose_checkout(&git, LoadMode::Prev(1), Parents::First, &tag_format).unwrap();
+        assert_eq!((id.as_str(), output.as_str()), ("c1", "checked out previous commit (c1), version 1.0.0 (tag v1.0.0)"));
+        git.head = 2;
+        assert!(choose_checkout(&git, LoadMode::Prev(1), Parents::First, &tag_format).is_err());
+    }
+
+    #[test]
+    fn test_choose_checkout_counts_and_releases() {
+        let tag_format = TagFormat::new("v{version}", "foo");
+        let mut git = FakeGit::new("main", &[
+            ("c6", &[], "docs: six"),
+            ("c5", &["v1.2.0"], "feat: five"),
+            ("c4", &["nightly"], "fix: four"),
+            ("c3", &[], "fix: three"),
+            ("c2", &["v1.1.0"], "feat: two"),
+            ("c1", &["v1.0.0"], "feat: one"),
+        ]);
+        git.head = 3;
+        git.detatched = true;
+        let checkout = |git: &FakeGit, load_mode: LoadMode| choose_checkout(git, load_mode, Parents::First, &tag_format).map(|(id, output, _)| (id, output));
+        assert_eq!(checkout(&git, LoadMode::PrevRelease(1)).unwrap(), (String::from("c2"), String::from("checked out previous release (c2), version 1.1.0 (tag v1.1.0)")));
+        assert_eq!(checkout(&git, LoadMode::PrevRelease(2)).unwrap(), (String::from("c1"), String::from("checked out the release 2 earlier than current (c1), version 1.0.0 (tag v1.0.0)")));
+        assert_eq!(checkout(&git, LoadMode::NextRelease(1)).unwrap().0, "c5");
+        assert_eq!(checkout(&git, LoadMode::Next(3)).unwrap().0, "c6");
+        assert_eq!(checkout(&git, LoadMode::Prev(2)).unwrap().0, "c1");
+        assert_eq!(checkout(&git, LoadMode::PrevRelease(3)).unwrap_err().to_string(), "fewer than 3 releases found earlier than current");
+        assert!(checkout(&git, LoadMode::NextRelease(2)).is_err());
+        git.head = 0;
+        assert_eq!(checkout(&git, LoadMode::NextRelease(1)).unwrap_err().to_string(), "no release found later than current");
+    }
+
+    #[test]
+    fn test_choose_checkout_at_time() {
+        let tag_format = TagFormat::new("v{version}", "foo");
+        let git = fake_history();
+        let day = 86_400;
+        let (id, output, _) = choose_checkout(&git, LoadMode::At(2 * day + 3_600), Parents::First, &tag_format).unwrap();
+        assert_eq!((id.as_str(), output.as_str()), ("c2", "checked out the last commit of main at or before 1970-01-03 01:00:00 UTC (c2, committed 1970-01-03 00:00:00 UTC), 1 commit after version 1.0.0 (tag v1.0.0)"));
+        assert_eq!(choose_checkout(&git, LoadMode::At(day), Parents::First, &tag_format).unwrap().0, "c1");
+        assert!(choose_checkout(&git, LoadMode::At(day - 1), Parents::First, &tag_format).is_err());
+    }
+
+    #[test]
+    fn test_find_load_stash() {
+        let stashes = vec![
+            (String::from("stash@{0}"), String::from("On feature: cargit load from feature")),
+            (String::from("stash@{1}"), String::from("WIP on main: c2 fix: two")),
+            (String::from("stash@{2}"), String::from("On (no branch): cargit load from main")),
+            (String::from("stash@{3}"), String::from("On main: cargit load from main")),
+        ];
+        assert_eq!(find_load_stash(&stashes, &load_stash_label("main")), Some(String::from("stash@{2}")));
+        assert_eq!(find_load_stash(&stashes, &load_stash_label("feat")), None);
+        assert_eq!(find_load_stash(&stashes[1..2], &load_stash_label("main")), None);
+    }
+
+    #[test]
+    fn test_resolve_version_tag() {
+        let git = FakeGit::new("main", &[
+            ("c5", &["v2.0.0-rc.1"], "feat!: five"),
+            ("c4", &["v1.5.0"], "feat: four"),
+            ("c3", &["v1.4.2", "stable"], "fix: three"),
+            ("c2", &["v1.4.1"], "fix: two"),
+            ("c1", &["v1.3.0"], "feat: one"),
+        ]);
+        let tag_format = TagFormat::new("v{version}", "foo");
+        let resolve = |id: &str| resolve_version_tag(&git, &tag_format, "main", String::from(id)).map(|(id, _)| id);
+        assert_eq!(resolve("1.4.1").unwrap(), "v1.4.1");
+        assert_eq!(resolve("c2").unwrap(), "c2");
+        assert!(resolve("9.9.9").is_err());
+        assert_eq!(resolve("1.4").unwrap(), "v1.4.2");
+        assert_eq!(resolve("<2.0").unwrap(), "v1.5.0");
+        assert_eq!(resolve("^1.3").unwrap(), "v1.5.0");
+        assert_eq!(resolve("~1.4.0").unwrap(), "v1.4.2");
+        assert_eq!(resolve(">=2.0.0-rc.1").unwrap(), "v2.0.0-rc.1");
+        assert!(resolve("1.6").is_err());
+        let (_, output) = resolve_version_tag(&git, &tag_format, "main", String::from("1.4")).unwrap();
+        assert_eq!(output, "checked out v1.4.2, version 1.4.2 is the highest matching '1.4'");
+    }
+}
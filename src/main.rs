@@ -1,18 +1,50 @@
+#![allow(clippy::needless_return)]
+
 mod save_process;
 mod load_process;
 mod internal;
+mod api_check;
+mod changelog;
+mod checks;
+mod config;
+mod conventional;
+mod date;
+mod feature_process;
+mod git;
+mod manifest;
+mod plan;
+mod registry;
+mod report;
+mod status_process;
+mod version_req;
+mod versions_process;
+mod workspace;
+mod worktree;
+
+use std::{env, process::ExitCode};
 
-use std::{env};
+use gmec::types::error_chain::ErrorChain;
 
-use gmec::types::error_chain::{ErrorChain, ErrorPropogation};
+use crate::internal::{recorded_failure, usage_error};
+use crate::report::*;
 
 use crate::save_process::save_process;
 use crate::load_process::load_process;
+use crate::changelog::changelog_process;
+use crate::feature_process::{finish_process, start_process};
+use crate::status_process::status_process;
+use crate::versions_process::versions_process;
 
 
 const SAVE_MODE_ARG: &str = "save";
 const LOAD_MODE_ARG: &str = "load";
 const HELP_MODE_ARG: &str = "help";
+const CHANGELOG_MODE_ARG: &str = "changelog";
+const STATUS_MODE_ARG: &str = "status";
+const VERSIONS_MODE_ARG: &str = "versions";
+const HISTORY_MODE_ARG: &str = "history";
+const START_MODE_ARG: &str = "start";
+const FINISH_MODE_ARG: &str = "finish";
 
 const HELP_MESSAGE :&str = "
 The cargit command is used to automate git and cargo processes that would normally take several independant commands,
@@ -20,45 +52,205 @@ while also making relevant changes to the crate's Cargo.toml and Cargo.lock (whe
 
 Usage:
   cargit help                                                    # Display this help message
-  cargit save [major|minor|patch] [-publish] [-m \"<message>\"]  # this command performs the following actions, in order:
+  cargit save [major|minor|patch|auto] [pre <label>|alpha|beta|rc] [release] [-build <metadata>] [-p <crate>...|--all] [-publish|--no-publish] [-m \"<message>\"]
+              [--remote <name>] [--branch <name>] [--tag-template <template>] [--annotate|--lightweight] [--sign]
+              [--dry-run] [--rollback soft|hard] [--skip-checks] [--no-changelog]
+              [--semver-check|--no-semver-check]
+                                                                 # this command performs the following actions, in order:
+      (Run the configured 'checks', eg. cargo fmt/clippy/test/doc, and stop with a summary if any fail,
+       before anything is changed. '--skip-checks' saves without running them)
       (Increment relevent part of the version in Cargo.toml, and set any lesser version parts to 0)
+      (With 'auto', pick the part from the Conventional Commits since the previous version tag and the new commit message:
+       a breaking change ('feat!:' or a 'BREAKING CHANGE:' footer) bumps major, or minor before 1.0.0, 'feat:' bumps minor
+       and anything else bumps patch. The reason for the choice is printed with the new version)
+      (If cargo-semver-checks is installed, compare the public API with the previous version tag and refuse a bump
+       that is too small for it, eg. a patch after removing a public function. 'auto' raises its choice instead.
+       '--semver-check' fails when it is not installed, '--no-semver-check' skips the comparison)
+      (With a pre-release label, move to the next pre-release: 1.2.3 -> 1.2.4-alpha.1, 1.0.0-beta.1 -> 1.0.0-beta.2 -> 1.0.0-rc.1)
+      (With 'release', promote a pre-release to its final version: 1.0.0-rc.3 -> 1.0.0)
+      (In a workspace, bump the '-p' crates, '--all' crates, or by default every crate changed since its last tag,
+       update path dependency versions between members, tag each crate as <crate>-v<version> and publish in dependency order.
+       Members with 'version.workspace = true' share one version, so saving one of them saves, tags and publishes
+       them all, and the others are listed with the new versions)
+      (Add a '## [<version>] - <date>' section to CHANGELOG.md (Keep a Changelog format) listing the commits since the
+       previous version tag and the new commit message, sorted into Added/Changed/Fixed/... by their 'feat:'/'fix:'
+       prefix or first word. Notes written by hand under '## [Unreleased]' are used instead when present)
       cargo generate-lockfile
-      git add .
+      git add --all
       git commit -m \"<message>\"
       git push
       git tag <Version from Cargo.toml>
+      (The tag name follows '--tag-template', eg. 'v{version}' or '{crate}-v{version}'. With '--annotate' the tag is
+       annotated with the commit message, and with '--sign' it is also signed with your git GPG/SSH signing key)
       git push --tags
       cargo publish (if -publish option set)
+      (With '--dry-run', print the new version, the manifest changes and every command instead of running them)
+      (If a step fails, the completed local steps are undone in reverse: the tag is deleted, the commit is reset
+       ('--rollback soft' keeps its changes, the default, 'hard' discards them) and Cargo.toml/Cargo.lock are restored.
+       Steps that already reached the remote (push, publish) cannot be undone and are reported instead.
+       Save commits every uncommitted change, so 'hard' would discard your own edits for good: it is refused
+       while the working tree has any, whether set by '--rollback' or by the configuration)
+
+  cargit load [prev|next|prev-release|next-release [<count>]|latest|<hash>|<tag>|<branch>|<version>|<requirement>]
+       [--at <date>|--ago <duration>] [--dry-run] [--all-parents] [--stash] [--worktree[=<path>] [--build]]
+                                                                 # git checkout the chosen commit
+      ('prev' and 'next' step along the current branch, or from a detatched HEAD along the branch it was detatched
+       from. Only first parents are followed, so a merge is one step; '--all-parents' also steps through the commits
+       of merged branches, newest first. 'prev-release' and 'next-release' skip to the nearest commit with a version
+       tag. A count moves that many commits or releases at once, eg. 'load prev 5' or 'load prev-release 2')
+      (A bare version such as 1.2.3 checks out its tag using the configured tag template, eg. v1.2.3. A partial
+       version or a requirement checks out the tag of the highest matching version in the branch history:
+       '1.4' means 1.4.x, and '^1.2', '~1.4.1', '<2.0' or '>=1.2, <1.5' follow Cargo's requirement syntax)
+      ('--at <date>' and '--ago <duration>' check out the last commit of the branch committed at or before that time.
+       Dates are UTC, eg. '2026-03-01' (the end of that day) or \"2026-03-01 14:30\", and durations look like '2w',
+       '3d' or '12h' (s, m, h, d, w, mo or y))
+      (With uncommitted changes, load asks to stash them as 'cargit load from <branch>' or stop. 'load latest' then
+       offers to re-apply the newest such stash of the branch. '--stash' answers yes to both without asking)
+      (With '--worktree', check it out in a new git worktree next to this one, eg. ../crate-v1.4.2, or at <path> with
+       '--worktree=<path>', and leave the current checkout as it is. '--build' then runs cargo build for this crate
+       inside it)
+      (With '--dry-run', print the commit that would be checked out and the command instead of running it)
+
+  cargit load --list-worktrees|--prune [--dry-run]               # list or remove the worktrees made by load --worktree
+      (Worktrees with uncommitted changes are kept by '--prune')
+
+  cargit changelog [--since <tag>]                               # print the changelog entries for the commits since the
+                                                                 # previous version tag (or <tag>), without saving
+
+  cargit status [--offline]                                      # show where the crate stands before a save
+      (The version in Cargo.toml, the latest version tag and the commits since it, whether that version is already
+       in the crates.io index (any sparse index set in CARGIT_REGISTRY_INDEX, skipped with '--offline'), the branch
+       or detatched HEAD, the changed files, how far the branch is ahead of or behind the remote, and what would stop
+       a save. At the root of a workspace with several members, each member is listed)
+
+  cargit versions [<branch>] [--graph] [--all-parents]           # list the releases of the branch, newest first
+      (Each version tag with its date, commit, author and subject, and the number of commits since the release
+       before it. 'history' is another name for it. '--graph' draws the tip of the branch (*), the releases (o) and
+       HEAD (@) in order, with the commits between them, to pick a 'load prev', 'load prev-release' or 'load <tag>'.
+       Only first parents are followed unless '--all-parents' is given, like load. The commits are counted along
+       that first-parent history, as 'load prev <count>' steps, so the counts are left out with '--all-parents')
+
+  cargit start <feature> [--from <branch>] [--dry-run]           # create a feature branch from the main branch and switch to it
+      (The main branch is 'main-branch' from the configuration, otherwise main or master. Uncommitted changes made
+       on the main branch are carried over to the new branch)
+
+  cargit finish [<feature>] [--merge|--squash [-m \"<message>\"]|--rebase] [--into <branch>] [--keep-branch]
+                [--skip-checks] [--dry-run] [--save [<save options>...]]
+                                                                 # merge the checked out (or named) feature branch back
+      (Run the configured 'checks' on the feature branch, checked out first when it is named, then bring it into
+       the main branch (or '--into <branch>'): '--merge' merges it (the default), '--squash' adds one commit with
+       all of its changes, described by '-m' or the list of squashed commits, and '--rebase' replays its commits on
+       top of the main branch and fast-forwards to them. The feature branch is then deleted, unless '--keep-branch'
+       is given)
+      (Everything after '--save' is passed to cargit save, which runs on the main branch without repeating the
+       checks, eg. 'cargit finish --squash --save minor -publish'. '--json' goes before '--save' to cover the merge)
+      (If a step fails, eg. on a merge conflict, finish stops there: resolve it, then complete the remaining steps
+       by hand or run finish again)
+
+  Every command also takes '--json' (or '--format json') among its options to print one JSON object instead, eg. for
+  release scripts ('cargit save patch --json'):
+      save: \"old_version\", \"new_version\", \"tag\", \"crates\", \"commit\", \"branch\", \"remote\", \"pushed\", \"published\",
+            \"steps_done\" (or \"steps_planned\" for a dry run) and \"steps_skipped\"
+      load: \"previous_commit\", \"ref\", \"commit\", \"branch\", \"worktree\", \"stashed\", \"reapplied\" and the steps,
+            or \"worktrees\"/\"pruned\" for '--list-worktrees'/'--prune'
+      changelog: \"since\" and the entries of each section in \"sections\"
+      status: \"version\", \"crates\", \"commit\", \"branch\", \"detatched\", \"changed_files\", \"remote\", \"ready\" and \"blockers\"
+      versions: \"branch\", \"tip\", \"head\", \"head_in_branch\", \"commits_after_latest\", \"head_release\",
+            \"commits_after_head_release\" and \"releases\", each with \"tag\", \"version\", \"commit\", \"date\", \"timestamp\",
+            \"author\", \"subject\", \"previous\" and \"commits_since_previous\"
+      start: \"branch\", \"from\", \"commit\" and the steps
+      finish: \"branch\", \"into\", \"strategy\", \"checks\", \"deleted\", \"commit\", the steps and \"save\" (the result of the save)
+  Each also has \"ok\", \"command\" and the text \"message\". Errors are {\"ok\": false, \"error\": {\"message\", \"chain\",
+  \"kind\", \"exit_code\"}}, also on stdout. Questions and the output of git and cargo go to stderr
+
+Configuration:
+  Defaults for save, start and finish can be set in a .cargit.toml file (crate or workspace root), in a
+  [package.metadata.cargit] or [workspace.metadata.cargit] table of Cargo.toml, or in a user config at
+  $XDG_CONFIG_HOME/cargit/config.toml (~/.config/cargit/config.toml). Crate settings override workspace settings,
+  which override user settings, and command line flags override them all:
+      bump = \"minor\"                # version part bumped when none is given
+      message = \"<message>\"         # default commit message
+      tag-template = \"v{version}\"   # tag name, '{crate}' is replaced by the crate name
+      remote = \"origin\"             # remote to push to
+      branch = \"main\"               # remote branch to push to
+      publish = true                # run cargo publish after pushing
+      rollback = \"soft\"             # how the commit is reset if a later step fails (\"hard\" needs a clean working tree)
+      annotate = true               # create annotated tags
+      sign = true                   # sign tags with your git signing key
+      changelog = false             # do not update CHANGELOG.md on save
+      semver-check = true           # require the public API check (false to never run it)
+      main-branch = \"main\"          # branch start creates feature branches from, and finish merges them into
+      merge-strategy = \"squash\"     # how finish merges a feature branch: \"merge\", \"squash\" or \"rebase\"
+      checks = [\"fmt\", \"clippy\", \"test\", \"doc\", \"cargo deny check\"]
+                                    # run before save or finish changes anything: the builtin cargo checks or any command
+
+Exit status:
+  0 on success, 2 for invalid arguments, 3 when a git command failed, 4 when a cargo command failed,
+  5 when stopped without doing what was asked: a refusal (eg. uncommitted changes or a detatched HEAD) or a 'no'
+  answer, and 1 for any other error.
+  The stderr of a failed command is repeated at the end of the error
 ";
 
 enum RunMode {
     HelpErr,
     Help,
     Save,
-    Load
+    Load,
+    Changelog,
+    Status,
+    Versions,
+    Start,
+    Finish
 }
 
 
 
-fn main() -> Result<(), ErrorChain> {
-    let mut args_iter  = env::args().collect::<Vec<String>>().into_iter();
-    let _ = args_iter.next().on_error("missing first cli argument")?;
+/// `cargit help`, which only looks at its arguments for the output format
+fn help_process<I>(mut args_iter: I) -> Result<Report, ErrorChain>
+where I: Iterator<Item = String> {
+    while let Some(next_arg) = args_iter.next() {
+        take_output_format(&next_arg, &mut args_iter)?;
+    }
+    return Ok(Report::new(HELP_MESSAGE));
+}
+
+fn main() -> ExitCode {
+    let mut args_iter = env::args().skip(1);
     let mode_arg = args_iter.next().unwrap_or(String::from("help"));
     let mode = match mode_arg.as_str() {
         SAVE_MODE_ARG => RunMode::Save,
         LOAD_MODE_ARG => RunMode::Load,
         HELP_MODE_ARG => RunMode::Help,
+        CHANGELOG_MODE_ARG => RunMode::Changelog,
+        STATUS_MODE_ARG => RunMode::Status,
+        VERSIONS_MODE_ARG | HISTORY_MODE_ARG => RunMode::Versions,
+        START_MODE_ARG => RunMode::Start,
+        FINISH_MODE_ARG => RunMode::Finish,
         _ => RunMode::HelpErr
     };
     let mode_result = match mode {
-        RunMode::HelpErr => Ok(format!("invalid mode passed to cargit, see 'cargit help' for more info:\n{}", HELP_MESSAGE)),
-        RunMode::Help => Ok(format!("{}", HELP_MESSAGE)),
+        RunMode::HelpErr => Err(usage_error(format!("invalid mode passed to cargit, see 'cargit help' for more info:\n{}", HELP_MESSAGE))),
+        RunMode::Help => help_process(args_iter),
         RunMode::Save => save_process(args_iter),
-        RunMode::Load => load_process(args_iter)
+        RunMode::Load => load_process(args_iter),
+        RunMode::Changelog => changelog_process(args_iter),
+        RunMode::Status => status_process(args_iter),
+        RunMode::Versions => versions_process(args_iter),
+        RunMode::Start => start_process(args_iter),
+        RunMode::Finish => finish_process(args_iter)
     };
     match mode_result {
-        Ok(message) => println!("{}", message),
-        Err(err) => eprintln!("{}", err)
+        Ok(report) => {
+            println!("{}", report.render(&mode_arg, output_format()));
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            let failure = recorded_failure();
+            match output_format() {
+                OutputFormat::Text => eprintln!("{}", err),
+                OutputFormat::Json => println!("{}", error_json(&mode_arg, &err, failure))
+            }
+            return ExitCode::from(failure.map(|kind| kind.exit_code()).unwrap_or(1));
+        }
     }
-    return Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,408 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use gmec::types::error_chain::{ErrorChain, ErrorPropogation};
+use toml_edit::{DocumentMut, Item, TableLike, Value};
+
+use crate::internal::*;
+use crate::version_req::*;
+
+const PACKAGE_TABLE: &str = "package";
+const WORKSPACE_TABLE: &str = "workspace";
+const VERSION_KEY: &str = "version";
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+const DEV_DEPENDENCY_TABLE: &str = "dev-dependencies";
+
+/// Where the `version` of a crate is actually written
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum VersionLocation {
+    /// `[package] version = "x.y.z"`
+    Package,
+    /// `[package] version.workspace = true`, resolved from `[workspace.package] version = "x.y.z"`
+    WorkspacePackage
+}
+
+/// A dependency of a manifest that points at another crate by `path`
+pub(crate) struct PathDependency {
+    /// Name of the depended upon package (the `package` key when the dependency is renamed)
+    pub package: String,
+    pub dev_only: bool
+}
+
+/// A Cargo.toml file that is edited in place, leaving comments, whitespace and key order untouched
+pub(crate) struct Manifest {
+    pub path: PathBuf,
+    /// Contents of the file when it was loaded
+    pub original: String,
+    document: DocumentMut
+}
+
+impl Manifest {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Manifest, ErrorChain> {
+        let path = path.as_ref().to_path_buf();
+        fs::metadata(&path).on_error(format!("No Cargo.toml file found at '{}'! This command must be run from a valid Rust crate root directory", path.display()))?;
+        let original = fs::read_to_string(&path).on_error(format!("{} could not be parsed to String", path.display()))?;
+        let document = Manifest::parse(&original).on_error(format!("{} is not a valid TOML document", path.display()))?;
+        return Ok(Manifest { path, original, document });
+    }
+
+    fn parse(manifest_str: &str) -> Result<DocumentMut, toml_edit::TomlError> {
+        return manifest_str.parse::<DocumentMut>();
+    }
+
+    pub fn is_workspace_root(&self) -> bool {
+        return self.document.contains_key(WORKSPACE_TABLE);
+    }
+
+    pub fn package_name(&self) -> Option<&str> {
+        return self.package_table()?.get("name")?.as_str();
+    }
+
+    /// `false` when the package sets `publish = false` or `publish = []`
+    pub fn is_publishable(&self) -> bool {
+        match self.package_table().and_then(|package| package.get("publish")) {
+            Some(publish) => {
+                if let Some(allowed) = publish.as_bool() {
+                    return allowed;
+                }
+                if let Some(registries) = publish.as_array() {
+                    return !registries.is_empty();
+                }
+                return true;
+            }
+            None => true
+        }
+    }
+
+    /// Lists dependencies written with a `path` key, including `workspace = true` dependencies
+    /// whose name is in `workspace_path_dependencies` (the path dependencies of `[workspace.dependencies]`)
+    pub fn path_dependencies(&self, workspace_path_dependencies: &[String]) -> Vec<PathDependency> {
+        let mut path_dependencies = Vec::new();
+        let mut tables: Vec<(&str, &dyn TableLike)> = Vec::new();
+        for table_name in DEPENDENCY_TABLES {
+            if let Some(table) = self.document.get(table_name).and_then(|item| item.as_table_like()) {
+                tables.push((table_name, table));
+            }
+        }
+        if let Some(targets) = self.document.get("target").and_then(|item| item.as_table_like()) {
+            for (_, target) in targets.iter() {
+                for table_name in DEPENDENCY_TABLES {
+                    if let Some(table) = target.get(table_name).and_then(|item| item.as_table_like()) {
+                        tables.push((table_name, table));
+                    }
+                }
+            }
+        }
+        for (table_name, table) in tables {
+            for (key, dependency) in table.iter() {
+                let dependency = match dependency.as_table_like() {
+                    Some(dependency) => dependency,
+                    None => continue
+                };
+                let package = dependency.get("package").and_then(|package| package.as_str()).unwrap_or(key).to_owned();
+                let is_path = dependency.contains_key("path");
+                let is_workspace_path = dependency.get(WORKSPACE_TABLE).and_then(|workspace| workspace.as_bool()) == Some(true)
+                    && workspace_path_dependencies.iter().any(|name| name == key);
+                if is_path || is_workspace_path {
+                    path_dependencies.push(PathDependency { package, dev_only: table_name == DEV_DEPENDENCY_TABLE });
+                }
+            }
+        }
+        return path_dependencies;
+    }
+
+    /// Names of the `[workspace.dependencies]` entries written with a `path` key
+    pub fn workspace_path_dependencies(&self) -> Vec<String> {
+        let workspace_dependencies = self.document.get(WORKSPACE_TABLE)
+            .and_then(|item| item.as_table_like())
+            .and_then(|workspace| workspace.get("dependencies"))
+            .and_then(|item| item.as_table_like());
+        return match workspace_dependencies {
+            Some(table) => table.iter()
+                .filter(|(_, dependency)| dependency.as_table_like().map(|dependency| dependency.contains_key("path")).unwrap_or(false))
+                .map(|(key, _)| key.to_owned())
+                .collect(),
+            None => Vec::new()
+        }
+    }
+
+    /// Rewrites the version requirement of every `path` dependency on `package` that is a single caret, tilde,
+    /// exact or bare version, keeping its operator (`=1.2.3` -> `=1.3.0`). Other requirements (`>=1.0, <2.0`)
+    /// are left as they are, and are an error when they no longer match `new_version`. Returns whether anything changed
+    pub fn set_path_dependency_version(&mut self, package: &str, new_version: &Version) -> Result<bool, ErrorChain> {
+        let mut changed = false;
+        let mut tables: Vec<&mut dyn TableLike> = Vec::new();
+        for (key, item) in self.document.iter_mut() {
+            let table = match item.as_table_like_mut() {
+                Some(table) => table,
+                None => continue
+            };
+            match key.get() {
+                name if DEPENDENCY_TABLES.contains(&name) => tables.push(table),
+                "target" => {
+                    for (_, target) in table.iter_mut() {
+                        if let Some(target) = target.as_table_like_mut() {
+                            for (target_key, target_item) in target.iter_mut() {
+                                if DEPENDENCY_TABLES.contains(&target_key.get()) {
+                                    if let Some(target_table) = target_item.as_table_like_mut() {
+                                        tables.push(target_table);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                WORKSPACE_TABLE => {
+                    if let Some(workspace_dependencies) = table.get_mut("dependencies").and_then(|item| item.as_table_like_mut()) {
+                        tables.push(workspace_dependencies);
+                    }
+                }
+                _ => {}
+            }
+        }
+        for table in tables {
+            for (key, dependency) in table.iter_mut() {
+                let dependency = match dependency.as_table_like_mut() {
+                    Some(dependency) => dependency,
+                    None => continue
+                };
+                let dependency_package = dependency.get("package").and_then(|package| package.as_str()).unwrap_or(key.get()).to_owned();
+                if dependency_package != package || !dependency.contains_key("path") {
+                    continue;
+                }
+                if let Some(requirement) = dependency.get_mut(VERSION_KEY).and_then(|item| item.as_value_mut()) {
+                    let old_requirement = match requirement.as_str() {
+                        Some(old_requirement) => old_requirement.to_owned(),
+                        None => continue
+                    };
+                    let new_requirement = match rewritable_operator(&old_requirement) {
+                        Some(operator) => format!("{}{}", operator, new_version),
+                        None => {
+                            let matched = VersionReq::parse(&old_requirement).on_error(format!("invalid version requirement on {} in {}", package, self.path.display()))?.matches(new_version);
+                            if !matched {
+                                return Err(ErrorChain::new(format!(
+                                    "the requirement '{}' on {} in {} does not match its new version {}, update it by hand",
+                                    old_requirement, package, self.path.display(), new_version
+                                )));
+                            }
+                            continue;
+                        }
+                    };
+                    if new_requirement != old_requirement {
+                        set_string_value_preserving_decor(requirement, new_requirement);
+                        changed = true;
+                    }
+                }
+            }
+        }
+        return Ok(changed);
+    }
+
+    /// The `[workspace.metadata.<tool>]` and `[package.metadata.<tool>]` tables, in that order
+    pub fn tool_metadata(&self, tool: &str) -> Vec<(&'static str, &dyn TableLike)> {
+        let mut tables: Vec<(&'static str, &dyn TableLike)> = Vec::new();
+        let workspace_metadata = self.document.get(WORKSPACE_TABLE).and_then(|item| item.as_table_like()).and_then(|workspace| workspace.get("metadata"));
+        if let Some(table) = workspace_metadata.and_then(|item| item.as_table_like()).and_then(|metadata| metadata.get(tool)).and_then(|item| item.as_table_like()) {
+            tables.push(("workspace.metadata", table));
+        }
+        let package_metadata = self.package_table().and_then(|package| package.get("metadata"));
+        if let Some(table) = package_metadata.and_then(|item| item.as_table_like()).and_then(|metadata| metadata.get(tool)).and_then(|item| item.as_table_like()) {
+            tables.push(("package.metadata", table));
+        }
+        return tables;
+    }
+
+    fn package_table(&self) -> Option<&dyn TableLike> {
+        return self.document.get(PACKAGE_TABLE)?.as_table_like();
+    }
+
+    fn workspace_package_table(&self) -> Option<&dyn TableLike> {
+        return self.document.get(WORKSPACE_TABLE)?.as_table_like()?.get(PACKAGE_TABLE)?.as_table_like();
+    }
+
+    /// Determines whether the package version is written in this manifest or inherited from the workspace
+    pub fn version_location(&self) -> Result<VersionLocation, ErrorChain> {
+        let package = self.package_table().on_error(format!("no [{}] table found in {}", PACKAGE_TABLE, self.path.display()))?;
+        let version = package.get(VERSION_KEY).on_error(format!("no '{}' key found in the [{}] table of {}", VERSION_KEY, PACKAGE_TABLE, self.path.display()))?;
+        if version.is_str() {
+            return Ok(VersionLocation::Package);
+        }
+        let inherits_workspace = version.as_table_like().and_then(|inherit| inherit.get(WORKSPACE_TABLE)).and_then(|workspace| workspace.as_bool());
+        if inherits_workspace == Some(true) {
+            return Ok(VersionLocation::WorkspacePackage);
+        }
+        return Err(ErrorChain::new(format!("the '{}' key in the [{}] table of {} must be a string or '{}.workspace = true'", VERSION_KEY, PACKAGE_TABLE, self.path.display(), VERSION_KEY)));
+    }
+
+    /// Reads the version string at `location`, which must be present in this manifest
+    pub fn version(&self, location: VersionLocation) -> Result<Version, ErrorChain> {
+        let version_str = self.version_item(location)?.as_str().on_error(format!("the version in {} is not a string", self.path.display()))?;
+        return Version::parse(version_str).on_error(format!("could not parse version '{}' in {}", version_str, self.path.display()));
+    }
+
+    fn version_item(&self, location: VersionLocation) -> Result<&Item, ErrorChain> {
+        let (table, table_name) = match location {
+            VersionLocation::Package => (self.package_table(), "package"),
+            VersionLocation::WorkspacePackage => (self.workspace_package_table(), "workspace.package")
+        };
+        let table = table.on_error(format!("no [{}] table found in {}", table_name, self.path.display()))?;
+        return table.get(VERSION_KEY).on_error(format!("no '{}' key found in the [{}] table of {}", VERSION_KEY, table_name, self.path.display()));
+    }
+
+    /// Rewrites only the version string at `location`, keeping its surrounding whitespace and comments
+    pub fn set_version(&mut self, location: VersionLocation, new_version: &Version) -> Result<(), ErrorChain> {
+        let path_display = self.path.display().to_string();
+        let table = match location {
+            VersionLocation::Package => self.document.get_mut(PACKAGE_TABLE).and_then(|item| item.as_table_like_mut()),
+            VersionLocation::WorkspacePackage => self.document.get_mut(WORKSPACE_TABLE)
+                .and_then(|item| item.as_table_like_mut())
+                .and_then(|workspace| workspace.get_mut(PACKAGE_TABLE))
+                .and_then(|item| item.as_table_like_mut())
+        };
+        let value = table.and_then(|table| table.get_mut(VERSION_KEY))
+            .and_then(|item| item.as_value_mut())
+            .filter(|value| value.is_str())
+            .on_error(format!("no version string found to update in {}", path_display))?;
+        set_string_value_preserving_decor(value, new_version.to_string());
+        return Ok(());
+    }
+
+    pub fn contents(&self) -> String {
+        return self.document.to_string();
+    }
+
+    pub fn is_changed(&self) -> bool {
+        return self.contents() != self.original;
+    }
+}
+
+pub(crate) fn set_string_value_preserving_decor(value: &mut Value, new_string: String) {
+    let decor = value.decor().clone();
+    *value = Value::from(new_string);
+    *value.decor_mut() = decor;
+}
+
+/// The operator of a requirement that names a single version with `^`, `~`, `=` or nothing (`0.3`, `=1.2.3`),
+/// `None` for ranges, wildcards and several comparators, which cannot just take a new version
+fn rewritable_operator(requirement: &str) -> Option<&str> {
+    let requirement = requirement.trim();
+    let operator = ["^", "~", "="].into_iter().find(|operator| requirement.starts_with(operator)).unwrap_or("");
+    let version = requirement[operator.len()..].trim();
+    let numbers = version.split_once('-').map(|(numbers, _)| numbers).unwrap_or(version);
+    let parts: Vec<&str> = numbers.split('.').collect();
+    if parts.len() > 3 || parts.iter().any(|part| part.is_empty() || !part.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+    return Some(operator);
+}
+
+/// Walks up from the directory containing `manifest_path` looking for the Cargo.toml that declares `[workspace]`
+pub(crate) fn find_workspace_root_manifest(manifest_path: &Path) -> Result<Option<PathBuf>, ErrorChain> {
+    let absolute_path = fs::canonicalize(manifest_path).on_error(format!("could not resolve the absolute path of {}", manifest_path.display()))?;
+    let mut search_dir = absolute_path.parent();
+    while let Some(dir) = search_dir {
+        let candidate = dir.join(CARGO_MANIFEST);
+        if candidate.is_file() && Manifest::load(&candidate)?.is_workspace_root() {
+            return Ok(Some(candidate));
+        }
+        search_dir = dir.parent();
+    }
+    return Ok(None);
+}
+
+/// Loads the manifest that actually holds the version of the crate at `manifest_path`,
+/// following `version.workspace = true` to the workspace root
+pub(crate) fn load_version_manifest(manifest_path: &Path) -> Result<(Manifest, VersionLocation), ErrorChain> {
+    let manifest = Manifest::load(manifest_path)?;
+    match manifest.version_location()? {
+        VersionLocation::Package => Ok((manifest, VersionLocation::Package)),
+        VersionLocation::WorkspacePackage => {
+            let root_path = find_workspace_root_manifest(manifest_path)?.on_error(format!("{} inherits its version from the workspace, but no workspace root was found", manifest_path.display()))?;
+            return Ok((Manifest::load(root_path)?, VersionLocation::WorkspacePackage));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(manifest_str: &str) -> Manifest {
+        return Manifest { path: PathBuf::from(CARGO_MANIFEST), original: manifest_str.to_owned(), document: Manifest::parse(manifest_str).unwrap() };
+    }
+
+    #[test]
+    fn test_set_version_only_touches_package_table() {
+        let manifest_str = r#"# version = "9.9.9"
+[dependencies]
+serde = { version = "1.0" } # keep me
+
+[package]
+name    = "foo"
+# the version below is bumped by cargit
+version   =   "0.4.0-beta.1"   # trailing comment
+edition = "2021"
+"#;
+        let mut manifest = manifest(manifest_str);
+        assert_eq!(manifest.version_location().unwrap(), VersionLocation::Package);
+        assert_eq!(manifest.version(VersionLocation::Package).unwrap().to_string(), "0.4.0-beta.1");
+        manifest.set_version(VersionLocation::Package, &Version::parse("0.4.0").unwrap()).unwrap();
+        assert_eq!(manifest.contents(), manifest_str.replace("0.4.0-beta.1", "0.4.0"));
+    }
+
+    #[test]
+    fn test_set_version_in_workspace_package() {
+        let member_str = "[package]\nname = \"foo\"\nversion.workspace = true\n";
+        assert_eq!(manifest(member_str).version_location().unwrap(), VersionLocation::WorkspacePackage);
+        let root_str = "[workspace]\nmembers = [\"foo\"]\n\n[workspace.package]\nversion = \"1.2.3\" # shared\n\n[workspace.dependencies]\nfoo = { version = \"1.2.3\", path = \"foo\" }\n";
+        let mut root = manifest(root_str);
+        assert!(root.is_workspace_root());
+        root.set_version(VersionLocation::WorkspacePackage, &Version::parse("1.3.0").unwrap()).unwrap();
+        assert_eq!(root.contents(), root_str.replacen("1.2.3", "1.3.0", 1));
+    }
+
+    #[test]
+    fn test_path_dependency_versions() {
+        let manifest_str = r#"[package]
+name = "foo"
+version = "0.1.0"
+
+[dependencies]
+bar = { path = "../bar", version = "=0.2.0" } # exact
+baz-renamed = { package = "baz", path = "../baz", version = "0.3" }
+serde = "1.0"
+
+[target.'cfg(unix)'.dev-dependencies]
+bar = { path = "../bar", version = "0.2.0" }
+"#;
+        let mut manifest = manifest(manifest_str);
+        let dependencies: Vec<(String, bool)> = manifest.path_dependencies(&[]).into_iter().map(|dep| (dep.package, dep.dev_only)).collect();
+        assert_eq!(dependencies, vec![("bar".to_owned(), false), ("baz".to_owned(), false), ("bar".to_owned(), true)]);
+        assert!(manifest.set_path_dependency_version("bar", &Version::parse("0.3.0").unwrap()).unwrap());
+        assert!(!manifest.set_path_dependency_version("serde", &Version::parse("2.0.0").unwrap()).unwrap());
+        assert_eq!(manifest.contents(), manifest_str.replace("=0.2.0", "=0.3.0").replace("\"0.2.0\"", "\"0.3.0\""));
+    }
+
+    #[test]
+    fn test_path_dependency_upper_bound_is_kept() {
+        let manifest_str = "[dependencies]\nbar = { path = \"../bar\", version = \"<2.0\" }\n";
+        let mut manifest = manifest(manifest_str);
+        assert!(!manifest.set_path_dependency_version("bar", &Version::parse("1.4.0").unwrap()).unwrap());
+        assert_eq!(manifest.contents(), manifest_str);
+        assert!(manifest.set_path_dependency_version("bar", &Version::parse("2.0.0").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_path_dependency_range_is_kept() {
+        let manifest_str = "[dependencies]\nbar = { path = \"../bar\", version = \">=1.0, <2.0\" }\n";
+        let mut manifest = manifest(manifest_str);
+        assert!(!manifest.set_path_dependency_version("bar", &Version::parse("1.5.0").unwrap()).unwrap());
+        assert_eq!(manifest.contents(), manifest_str);
+        assert!(manifest.set_path_dependency_version("bar", &Version::parse("2.0.0").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_missing_version_is_an_error() {
+        assert!(manifest("[package]\nname = \"foo\"\n").version_location().is_err());
+        assert!(manifest("[dependencies]\nversion = \"1.0.0\"\n").version_location().is_err());
+    }
+}
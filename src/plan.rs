@@ -0,0 +1,277 @@
+use std::{fmt::{self, Display}, fs, io, path::PathBuf};
+
+use gmec::types::error_chain::{ErrorChain, ErrorPropogation};
+
+use crate::internal::*;
+
+/// How a completed [`PlanStep`] is reverted when a later step fails
+#[derive(Clone)]
+pub(crate) enum Undo {
+    /// The step changed nothing that needs reverting
+    Nothing,
+    Run(CliCommand),
+    /// Put a file back the way it was, deleting it if it did not exist (`None`)
+    RestoreFile { path: PathBuf, original: Option<String> },
+    /// The step changed something outside this repository (eg. a push or a publish)
+    Irreversible
+}
+
+impl Undo {
+    /// Captures the current contents of `path` so it can be restored later
+    pub fn restore_file(path: PathBuf) -> Undo {
+        let original = fs::read_to_string(&path).ok();
+        return Undo::RestoreFile { path, original };
+    }
+
+    fn execute(&self) -> Result<(), ErrorChain> {
+        match self {
+            Undo::Nothing | Undo::Irreversible => Ok(()),
+            Undo::Run(command) => command.run().on_error(format!("command failed: {}", command)),
+            Undo::RestoreFile { path, original: Some(original) } => fs::write(path, original).on_error(format!("failed to restore {}", path.display())),
+            Undo::RestoreFile { path, original: None } => match fs::remove_file(path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(ErrorChain::from(err, format!("failed to remove {}", path.display()))),
+                _ => Ok(())
+            }
+        }
+    }
+}
+
+impl Display for Undo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Undo::Nothing => write!(f, "nothing to undo"),
+            Undo::Run(command) => write!(f, "{}", command),
+            Undo::RestoreFile { path, original: Some(_) } => write!(f, "restore {}", path.display()),
+            Undo::RestoreFile { path, original: None } => write!(f, "remove {}", path.display()),
+            Undo::Irreversible => write!(f, "cannot be undone")
+        }
+    }
+}
+
+/// One action of a [`Plan`]
+pub(crate) enum PlanStep {
+    /// Replace the contents of a file (eg. a bumped Cargo.toml), or create it when `original` is `None`
+    WriteFile { path: PathBuf, original: Option<String>, contents: String },
+    Run { command: CliCommand, undo: Undo }
+}
+
+impl PlanStep {
+    pub fn execute(&self) -> Result<(), ErrorChain> {
+        match self {
+            PlanStep::WriteFile { path, contents, .. } => fs::write(path, contents).on_error(format!("failed to write to {}", path.display())),
+            PlanStep::Run { command, .. } => command.run().on_error(format!("command failed: {}", command))
+        }
+    }
+
+    pub fn undo(&self) -> Undo {
+        match self {
+            PlanStep::WriteFile { path, original, .. } => Undo::RestoreFile { path: path.clone(), original: original.clone() },
+            PlanStep::Run { undo, .. } => undo.clone()
+        }
+    }
+
+    fn summary(&self) -> String {
+        match self {
+            PlanStep::WriteFile { path, original: Some(_), .. } => format!("write {}", path.display()),
+            PlanStep::WriteFile { path, original: None, .. } => format!("create {}", path.display()),
+            PlanStep::Run { command, .. } => command.to_string()
+        }
+    }
+}
+
+impl Display for PlanStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.summary())?;
+        if let PlanStep::WriteFile { original, contents, .. } = self {
+            for line in line_diff(original.as_deref().unwrap_or_default(), contents) {
+                write!(f, "\n    {}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An ordered list of file writes and commands, built up front so it can be printed
+/// for a dry run or executed for real
+pub(crate) struct Plan {
+    pub steps: Vec<PlanStep>,
+    /// When set, a failing step reverts the completed ones (see [`Plan::execute`])
+    pub transactional: bool
+}
+
+impl Plan {
+    pub fn new() -> Plan {
+        return Plan { steps: Vec::new(), transactional: false };
+    }
+
+    pub fn transactional() -> Plan {
+        return Plan { steps: Vec::new(), transactional: true };
+    }
+
+    pub fn write_file(&mut self, path: PathBuf, original: Option<String>, contents: String) {
+        self.steps.push(PlanStep::WriteFile { path, original, contents });
+    }
+
+    pub fn run(&mut self, command: CliCommand) {
+        self.steps.push(PlanStep::Run { command, undo: Undo::Nothing });
+    }
+
+    pub fn run_with_undo(&mut self, command: CliCommand, undo: Undo) {
+        self.steps.push(PlanStep::Run { command, undo });
+    }
+
+    /// One line per step, without the file diffs of [`Display`]
+    pub fn summaries(&self) -> Vec<String> {
+        return self.steps.iter().map(|step| step.summary()).collect();
+    }
+
+    /// Executes every step in order. For a transactional plan, a failure undoes the completed
+    /// steps in reverse order, back to the last irreversible one: once something has reached a
+    /// remote, the local steps it depends on (commit, tag) are kept so both sides stay consistent
+    pub fn execute(&self) -> Result<(), ErrorChain> {
+        for (index, step) in self.steps.iter().enumerate() {
+            if let Err(err) = step.execute() {
+                if !self.transactional {
+                    return Err(err);
+                }
+                let report = self.rollback(index);
+                return Err(ErrorChain::from(err, report));
+            }
+        }
+        return Ok(());
+    }
+
+    fn rollback(&self, failed_index: usize) -> String {
+        let completed = &self.steps[..failed_index];
+        let last_irreversible = completed.iter().rposition(|step| matches!(step.undo(), Undo::Irreversible));
+        let rollback_from = last_irreversible.map(|index| index + 1).unwrap_or(0);
+        let mut report = format!("step {} failed: {}", failed_index + 1, self.steps[failed_index].summary());
+        let mut rolled_back = Vec::new();
+        let mut rollback_failed = Vec::new();
+        for step in completed[rollback_from..].iter().rev() {
+            let undo = step.undo();
+            if let Undo::Nothing = undo {
+                continue;
+            }
+            match undo.execute() {
+                Ok(()) => rolled_back.push(format!("{} (undid '{}')", undo, step.summary())),
+                Err(err) => rollback_failed.push(format!("{} (to undo '{}'): {}", undo, step.summary(), err))
+            }
+        }
+        if rolled_back.is_empty() && rollback_failed.is_empty() && last_irreversible.is_none() {
+            report.push_str("\nnothing needed to be rolled back");
+        }
+        if !rolled_back.is_empty() {
+            report.push_str("\nrolled back:");
+            for line in rolled_back {
+                report.push_str(format!("\n  - {}", line).as_str());
+            }
+        }
+        if !rollback_failed.is_empty() {
+            report.push_str("\nFAILED to roll back, fix these by hand:");
+            for line in rollback_failed {
+                report.push_str(format!("\n  - {}", line).as_str());
+            }
+        }
+        if let Some(last_irreversible) = last_irreversible {
+            report.push_str("\ncould not be undone (already applied to a remote):");
+            for step in completed[..=last_irreversible].iter().filter(|step| matches!(step.undo(), Undo::Irreversible)) {
+                report.push_str(format!("\n  - {}", step.summary()).as_str());
+            }
+            report.push_str("\nkept, because they were already pushed or published:");
+            for step in completed[..last_irreversible].iter().filter(|step| !matches!(step.undo(), Undo::Irreversible | Undo::Nothing)) {
+                report.push_str(format!("\n  - {}", step.summary()).as_str());
+            }
+        }
+        return report;
+    }
+}
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Planned steps:")?;
+        for (index, step) in self.steps.iter().enumerate() {
+            write!(f, "\n  {}. {}", index + 1, step)?;
+        }
+        Ok(())
+    }
+}
+
+/// Minimal line based diff of `before` and `after` (longest common subsequence), only listing changed lines
+pub(crate) fn line_diff(before: &str, after: &str) -> Vec<String> {
+    let before_lines: Vec<&str> = before.lines().collect();
+    let after_lines: Vec<&str> = after.lines().collect();
+    let (b_len, a_len) = (before_lines.len(), after_lines.len());
+    let mut common = vec![vec![0usize; a_len + 1]; b_len + 1];
+    for b in (0..b_len).rev() {
+        for a in (0..a_len).rev() {
+            common[b][a] = if before_lines[b] == after_lines[a] {
+                common[b + 1][a + 1] + 1
+            } else {
+                common[b + 1][a].max(common[b][a + 1])
+            };
+        }
+    }
+    let mut diff = Vec::new();
+    let (mut b, mut a) = (0, 0);
+    let mut in_hunk = false;
+    while b < b_len || a < a_len {
+        if b < b_len && a < a_len && before_lines[b] == after_lines[a] {
+            b += 1;
+            a += 1;
+            in_hunk = false;
+            continue;
+        }
+        if !in_hunk {
+            diff.push(format!("@@ line {} @@", a + 1));
+            in_hunk = true;
+        }
+        if b < b_len && (a == a_len || common[b + 1][a] >= common[b][a + 1]) {
+            diff.push(format!("-{}", before_lines[b]));
+            b += 1;
+        } else {
+            diff.push(format!("+{}", after_lines[a]));
+            a += 1;
+        }
+    }
+    return diff;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_diff() {
+        let before = "[package]\nname = \"foo\"\nversion = \"1.2.3\"\nedition = \"2021\"\n";
+        let after = "[package]\nname = \"foo\"\nversion = \"1.3.0\"\nedition = \"2021\"\n";
+        assert_eq!(line_diff(before, after), vec!["@@ line 3 @@", "-version = \"1.2.3\"", "+version = \"1.3.0\""]);
+        assert!(line_diff(before, before).is_empty());
+    }
+
+    #[test]
+    fn test_plan_display() {
+        let mut plan = Plan::new();
+        plan.run(git_commit_with_message("release notes"));
+        plan.run(git_create_tag("1.3.0"));
+        assert_eq!(plan.to_string(), "Planned steps:\n  1. git commit -m \"release notes\"\n  2. git tag 1.3.0");
+    }
+
+    #[test]
+    fn test_transactional_plan_restores_files() {
+        let dir = std::env::temp_dir().join(format!("cargit-plan-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let manifest_path = dir.join(CARGO_MANIFEST);
+        fs::write(&manifest_path, "version = \"1.0.0\"\n").unwrap();
+        let mut plan = Plan::transactional();
+        plan.write_file(manifest_path.clone(), Some("version = \"1.0.0\"\n".to_owned()), "version = \"1.0.1\"\n".to_owned());
+        plan.write_file(dir.join("CHANGELOG.md"), None, "# Changelog\n".to_owned());
+        plan.run(CliCommand::new("git", &["--cargit-invalid-option"]));
+        let err = plan.execute().unwrap_err().to_string();
+        assert!(err.starts_with("step 3 failed"), "{}", err);
+        assert!(err.contains("rolled back:"), "{}", err);
+        assert_eq!(fs::read_to_string(&manifest_path).unwrap(), "version = \"1.0.0\"\n");
+        assert!(!dir.join("CHANGELOG.md").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,95 @@
+use std::env;
+
+use crate::internal::*;
+
+/// The sparse index of crates.io, overridden by `CARGIT_REGISTRY_INDEX` (eg. a mirror, or a `file://` copy)
+const CRATES_IO_INDEX: &str = "https://index.crates.io";
+const REGISTRY_INDEX_ENV: &str = "CARGIT_REGISTRY_INDEX";
+
+/// Whether a version of a crate is in the registry index
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) enum Published {
+    Yes { yanked: bool },
+    /// The crate has no such version, or was never published at all
+    No,
+    /// The index could not be read, with the reason
+    Unknown(String)
+}
+
+/// Path of a crate's file in a sparse index: `1/a`, `2/ab`, `3/a/abc`, or `ab/cd/abcd...` from the lowercased name
+fn index_path(name: &str) -> String {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name)
+    }
+}
+
+/// Looks up `version` of the crate `name` in the registry index with curl
+pub(crate) fn published_state(name: &str, version: &Version) -> Published {
+    let index = env::var(REGISTRY_INDEX_ENV).unwrap_or(String::from(CRATES_IO_INDEX));
+    let url = format!("{}/{}", index.trim_end_matches('/'), index_path(name));
+    let output = match get_cli_output("curl", &["--silent", "--show-error", "--location", "--max-time", "10", "--write-out", "\n%{http_code}", url.as_str()]) {
+        Ok(output) => output,
+        Err(_) => return Published::Unknown(String::from("curl is not installed"))
+    };
+    // 37: a `file://` index without the crate's file
+    if output.status.code() == Some(37) {
+        return Published::No;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (entries, status) = stdout.rsplit_once('\n').unwrap_or(("", &stdout));
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Published::Unknown(format!("could not read {}: {}", url, stderr.trim()));
+    }
+    return match status.trim() {
+        "200" | "000" => find_version(entries, version),
+        "404" | "410" => Published::No,
+        status => Published::Unknown(format!("{} answered with HTTP status {}", url, status))
+    };
+}
+
+/// Finds `version` among the JSON lines of an index file, one line per published version
+fn find_version(entries: &str, version: &Version) -> Published {
+    let version = version.to_string();
+    for entry in entries.lines() {
+        if top_level_value(entry, "vers") == Some(version.as_str()) {
+            return Published::Yes { yanked: top_level_value(entry, "yanked") == Some("true") };
+        }
+    }
+    return Published::No;
+}
+
+/// The value of `key` in a one line JSON object, without quotes for a string. Only for keys that never appear
+/// nested, such as `vers` and `yanked` in an index entry
+fn top_level_value<'a>(entry: &'a str, key: &str) -> Option<&'a str> {
+    let (_, rest) = entry.split_once(&format!("\"{}\"", key))?;
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    return match rest.strip_prefix('"') {
+        Some(string) => string.split_once('"').map(|(value, _)| value),
+        None => rest.split([',', '}']).next().map(|value| value.trim())
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_entries() {
+        assert_eq!(index_path("a"), "1/a");
+        assert_eq!(index_path("io"), "2/io");
+        assert_eq!(index_path("Syn"), "3/s/syn");
+        assert_eq!(index_path("cargit"), "ca/rg/cargit");
+        let entries = concat!(
+            r#"{"name":"cargit","vers":"0.3.7","deps":[{"name":"gmec","req":"^0.0.3"}],"cksum":"00","features":{},"yanked":true}"#, "\n",
+            r#"{"name": "cargit", "vers": "0.3.8", "deps": [], "cksum": "00", "features": {}, "yanked": false}"#, "\n"
+        );
+        assert_eq!(find_version(entries, &Version::parse("0.3.8").unwrap()), Published::Yes { yanked: false });
+        assert_eq!(find_version(entries, &Version::parse("0.3.7").unwrap()), Published::Yes { yanked: true });
+        assert_eq!(find_version(entries, &Version::parse("0.3.9").unwrap()), Published::No);
+    }
+}
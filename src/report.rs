@@ -0,0 +1,226 @@
+use std::{fmt::{self, Display, Write as _}, io::{self, Write}, sync::atomic::{AtomicBool, Ordering}};
+
+use gmec::types::error_chain::ErrorChain;
+
+use crate::internal::*;
+
+pub(crate) const FORMAT_JSON_ARG: &str = "--json";
+pub(crate) const FORMAT_ARG: &str = "--format";
+
+/// How the result of a command is printed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum OutputFormat {
+    Text,
+    /// One JSON object on stdout, with prompts and the output of the commands run moved to stderr
+    Json
+}
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+impl OutputFormat {
+    pub fn parse(format: &str) -> Option<OutputFormat> {
+        match format.to_lowercase().as_str() {
+            "text" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            _ => None
+        }
+    }
+}
+
+/// Reads `--json`, or `--format` and the `text` or `json` after it, when `arg` is one of them, and keeps the chosen
+/// format for the rest of the run. Each mode calls it on its own options, so the value of an option (`-m --json`)
+/// is never taken for it. Returns whether `arg` was an output format option
+pub(crate) fn take_output_format<I>(arg: &str, args_iter: &mut I) -> Result<bool, ErrorChain>
+where I: Iterator<Item = String> {
+    let format = match arg {
+        FORMAT_JSON_ARG => OutputFormat::Json,
+        FORMAT_ARG => {
+            let name = args_iter.next().ok_or_else(|| usage_error(format!("the '{}' option must be followed by 'text' or 'json'", FORMAT_ARG)))?;
+            OutputFormat::parse(&name).ok_or_else(|| usage_error(format!("invalid output format '{}', expected 'text' or 'json'", name)))?
+        }
+        _ => return Ok(false)
+    };
+    JSON_OUTPUT.store(format == OutputFormat::Json, Ordering::Relaxed);
+    return Ok(true);
+}
+
+pub(crate) fn output_format() -> OutputFormat {
+    return match JSON_OUTPUT.load(Ordering::Relaxed) {
+        true => OutputFormat::Json,
+        false => OutputFormat::Text
+    };
+}
+
+/// Prints a progress line or a question for the user without a newline: on stdout for text output, and on
+/// stderr for JSON so stdout only holds the result
+pub(crate) fn print_message(message: &str) {
+    let _ = match output_format() {
+        OutputFormat::Text => io::stdout().write_all(message.as_bytes()).and_then(|_| io::stdout().flush()),
+        OutputFormat::Json => io::stderr().write_all(message.as_bytes())
+    };
+}
+
+/// A JSON value, rendered compactly by [`Display`]
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>)
+}
+
+impl Json {
+    pub fn str(value: impl Display) -> Json {
+        return Json::String(value.to_string());
+    }
+
+    /// `null` for `None`
+    pub fn opt_str(value: Option<impl Display>) -> Json {
+        return value.map(Json::str).unwrap_or(Json::Null);
+    }
+
+    pub fn strs<S: Display>(values: impl IntoIterator<Item = S>) -> Json {
+        return Json::Array(values.into_iter().map(Json::str).collect());
+    }
+
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        return Json::Object(fields.into_iter().map(|(key, value)| (key.to_owned(), value)).collect());
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(value) => write!(f, "{}", value),
+            Json::String(value) => write_json_string(f, value),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_json_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_json_string(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?
+        }
+    }
+    return f.write_char('"');
+}
+
+/// The result of a command: the sentence printed as text, and the fields of its JSON object
+pub(crate) struct Report {
+    message: String,
+    fields: Vec<(String, Json)>
+}
+
+impl Report {
+    pub fn new(message: impl Into<String>) -> Report {
+        return Report { message: message.into(), fields: Vec::new() };
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Report {
+        self.message = message.into();
+        return self;
+    }
+
+    pub fn field(mut self, key: &str, value: Json) -> Report {
+        self.fields.push((key.to_owned(), value));
+        return self;
+    }
+
+    pub fn message(&self) -> &str {
+        return &self.message;
+    }
+
+    /// The fields and the message as one object, without `ok` and `command`, to nest in the report of another command
+    pub fn to_json(&self) -> Json {
+        let mut fields = self.fields.clone();
+        fields.push((String::from("message"), Json::str(&self.message)));
+        return Json::Object(fields);
+    }
+
+    pub fn render(&self, command: &str, format: OutputFormat) -> String {
+        match format {
+            OutputFormat::Text => self.message.clone(),
+            OutputFormat::Json => {
+                let mut fields = vec![(String::from("ok"), Json::Bool(true)), (String::from("command"), Json::str(command))];
+                fields.extend(self.fields.iter().cloned());
+                fields.push((String::from("message"), Json::str(&self.message)));
+                Json::Object(fields).to_string()
+            }
+        }
+    }
+}
+
+/// The separator gmec puts between an error's context and its cause
+const ERROR_CHAIN_SEPARATOR: &str = "\n\\ \\ \\\n";
+
+/// An error as a JSON object: `message` is the outermost context and `chain` every context down to the root cause
+pub(crate) fn error_json(command: &str, err: &ErrorChain, failure: Option<FailureKind>) -> Json {
+    let rendered = err.to_string();
+    let chain: Vec<&str> = rendered.split(ERROR_CHAIN_SEPARATOR).collect();
+    return Json::object(vec![
+        ("ok", Json::Bool(false)),
+        ("command", Json::str(command)),
+        ("error", Json::object(vec![
+            ("message", Json::str(chain[0])),
+            ("chain", Json::strs(chain.iter())),
+            ("kind", Json::str(failure.map(|kind| kind.name()).unwrap_or("error"))),
+            ("exit_code", Json::Number(failure.map(|kind| kind.exit_code()).unwrap_or(1) as i64))
+        ]))
+    ]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_json() {
+        let report = Report::new("Saved!\nNew version: 1.2.3")
+            .field("new_version", Json::str("1.2.3"))
+            .field("remote", Json::opt_str(None::<&str>))
+            .field("steps_done", Json::strs(["git commit -m \"fix \\ things\"", "git\ttag"]));
+        assert_eq!(report.render("save", OutputFormat::Text), "Saved!\nNew version: 1.2.3");
+        assert_eq!(report.render("save", OutputFormat::Json), concat!(
+            r#"{"ok":true,"command":"save","new_version":"1.2.3","remote":null,"#,
+            r#""steps_done":["git commit -m \"fix \\ things\"","git\ttag"],"message":"Saved!\nNew version: 1.2.3"}"#
+        ));
+        let err = ErrorChain::from(ErrorChain::new("exit code 1\u{1}"), "command failed: git push");
+        assert_eq!(error_json("save", &err, Some(FailureKind::Git)).to_string(), concat!(
+            r#"{"ok":false,"command":"save","error":{"message":"command failed: git push","#,
+            r#""chain":["command failed: git push","exit code 1\u0001"],"kind":"git","exit_code":3}}"#
+        ));
+    }
+}
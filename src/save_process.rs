@@ -1,66 +1,318 @@
-use std::{fs};
+use std::{collections::BTreeMap, env, fs, path::{Path, PathBuf}};
 
 use gmec::types::error_chain::ErrorChain;
 use gmec::types::error_chain::ErrorPropogation;
 
+use crate::api_check::*;
+use crate::changelog::*;
+use crate::checks::*;
+use crate::config::*;
+use crate::conventional::*;
+use crate::date::*;
+use crate::git::*;
 use crate::internal::*;
+use crate::manifest::*;
+use crate::plan::*;
+use crate::report::*;
+use crate::workspace::*;
 
 const SAVE_MAJOR :&str = "major";
 const SAVE_MINOR :&str = "minor";
 const SAVE_PATCH :&str = "patch";
+const SAVE_PRE :&str = "pre";
+const SAVE_ALPHA :&str = "alpha";
+const SAVE_BETA :&str = "beta";
+const SAVE_RC :&str = "rc";
+const SAVE_RELEASE :&str = "release";
+const SAVE_AUTO :&str = "auto";
 
 const SAVE_WITH_MSG: &str = "-m";
 const SAVE_PUBLISH: &str = "-publish";
+const SAVE_BUILD_METADATA: &str = "-build";
+const SAVE_PACKAGE: &str = "-p";
+const SAVE_ALL_PACKAGES: &str = "--all";
+const SAVE_DRY_RUN: &str = "--dry-run";
+const SAVE_NO_PUBLISH: &str = "--no-publish";
+const SAVE_REMOTE: &str = "--remote";
+const SAVE_BRANCH: &str = "--branch";
+const SAVE_TAG_TEMPLATE: &str = "--tag-template";
+const SAVE_ANNOTATE: &str = "--annotate";
+const SAVE_LIGHTWEIGHT: &str = "--lightweight";
+const SAVE_SIGN: &str = "--sign";
+const SAVE_ROLLBACK: &str = "--rollback";
+const SAVE_SKIP_CHECKS: &str = "--skip-checks";
+const SAVE_NO_CHANGELOG: &str = "--no-changelog";
+const SAVE_SEMVER_CHECK: &str = "--semver-check";
+const SAVE_NO_SEMVER_CHECK: &str = "--no-semver-check";
+const ROLLBACK_SOFT: &str = "soft";
+const ROLLBACK_HARD: &str = "hard";
 
-const DEFAULT_MSG: &str = "(undocumented change)";
+const CARGO_LOCKFILE: &str = "Cargo.lock";
 
 struct SaveModeOptions {
     update_part: Option<VersionPart>,
+    /// Choose `update_part` from the Conventional Commits since the previous version tag
+    auto: bool,
+    pre_release_label: Option<String>,
+    release: bool,
+    build_metadata: Option<String>,
+    packages: Vec<String>,
+    all_packages: bool,
     commit_message: Option<String>,
-    new_version: Option<Version>,
-    publish_after_push: bool,
+    publish_after_push: Option<bool>,
+    remote: Option<String>,
+    branch: Option<String>,
+    tag_template: Option<String>,
+    annotate: Option<bool>,
+    sign: Option<bool>,
+    dry_run: bool,
+    rollback_mode: Option<ResetMode>,
+    skip_checks: bool,
+    checks: Vec<Check>,
+    changelog: Option<bool>,
+    /// Compare the public API with the previous version tag: `None` runs the check only if `cargo semver-checks` is installed
+    semver_check: Option<bool>,
+}
+
+/// A crate whose version was bumped by this save
+struct SavedCrate {
+    /// `None` when saving a single crate rather than members of a workspace
+    name: Option<String>,
+    old_version: Version,
+    new_version: Version,
+    tag: String,
+    /// Used to find the tag of the previous version
+    tag_format: TagFormat,
+    /// Directory of the crate, relative to the current one (empty for the current crate)
+    dir: PathBuf,
+    publish: bool,
+    /// Why `auto` chose the bumped version part
+    bump_reason: Option<String>,
+    /// `false` for a workspace member saved only because it shares the `[workspace.package]` version of a selected one
+    selected: bool,
 }
 
 impl SaveModeOptions {
     fn blank() -> SaveModeOptions {
         return SaveModeOptions { 
             update_part: None, 
+            auto: false,
+            pre_release_label: None,
+            release: false,
+            build_metadata: None,
+            packages: Vec::new(),
+            all_packages: false,
             commit_message: None, 
-            new_version: None,
-            publish_after_push: false, 
+            publish_after_push: None, 
+            remote: None,
+            branch: None,
+            tag_template: None,
+            annotate: None,
+            sign: None,
+            dry_run: false,
+            rollback_mode: None,
+            skip_checks: false,
+            checks: Vec::new(),
+            changelog: None,
+            semver_check: None,
         }
     }
+
+    /// Fills in every option not given on the command line from the config files
+    fn apply_config(&mut self, config: CargitConfig) {
+        if self.update_part.is_none() && self.pre_release_label.is_none() && !self.release && !self.auto {
+            self.update_part = config.bump;
+        }
+        self.commit_message = self.commit_message.take().or(config.message);
+        self.publish_after_push = self.publish_after_push.or(config.publish);
+        self.remote = self.remote.take().or(config.remote);
+        self.branch = self.branch.take().or(config.branch);
+        self.tag_template = self.tag_template.take().or(config.tag_template);
+        self.rollback_mode = self.rollback_mode.or(config.rollback);
+        self.annotate = self.annotate.or(config.annotate);
+        self.sign = self.sign.or(config.sign);
+        self.checks = config.checks.unwrap_or_default();
+        self.changelog = self.changelog.or(config.changelog);
+        self.semver_check = self.semver_check.or(config.semver_check);
+    }
 }
 
-pub(crate) fn save_process<I>(mut args_iter: I) -> Result<String, ErrorChain>
+pub(crate) fn save_process<I>(mut args_iter: I) -> Result<Report, ErrorChain>
 where I: Iterator<Item = String> {
-    let mut options = process_args(&mut args_iter)?;
-    process_cargo_changes(&mut options)?;
-    if is_detatched_mode()? {
-        handle_detatched_state()?;
-    }
-    cargo_generate_lockfile()?;
-    git_stage_all_changes()?;
-    git_commit_with_message(&options.commit_message.unwrap_or(DEFAULT_MSG.to_owned()))?;
-    let mut final_message = String::from("Saved, Committed");
-    if let Some(new_version) = options.new_version {
-        git_create_tag(&new_version.to_string())?;
-        final_message.push_str(", Tagged");
-    }
-    if let Some(remote_name) = get_remote_name()? {
-        let branch_name = get_branch_name()?;
-        git_push(&remote_name, &branch_name)?;
+    let mut options = process_args(&mut args_iter).inspect_err(|_| record_failure(FailureKind::Usage))?;
+    options.apply_config(load_config()?);
+    let git = open_git_backend()?;
+    let git = git.as_ref();
+    // Save commits every change in the working tree, so a hard reset on rollback would erase the user's own edits
+    if options.rollback_mode == Some(ResetMode::Hard) && !git.changed_files().on_error("could not read the status of the working tree")?.is_empty() {
+        return Err(aborted(format!(
+            "Save aborted, '{} {}' would erase your uncommitted changes along with the version bump if a later step fails. Commit them first, or use '{} {}'",
+            SAVE_ROLLBACK, ROLLBACK_HARD, SAVE_ROLLBACK, ROLLBACK_SOFT
+        )));
+    }
+    let (saved_crates, manifests) = process_cargo_changes(git, &options)?;
+    let checks: &[Check] = if options.skip_checks { &[] } else { &options.checks };
+    let mut skipped = Vec::new();
+    if options.skip_checks && !options.checks.is_empty() {
+        skipped.push(format!("{} configured checks ({})", options.checks.len(), SAVE_SKIP_CHECKS));
+    }
+    if !options.dry_run {
+        run_checks(checks).on_error(format!("Save aborted, fix the failures or use '{}' to save anyway", SAVE_SKIP_CHECKS))?;
+    }
+    let is_detatched = git.is_detatched_mode()?;
+    let new_branch = if is_detatched && !options.dry_run { Some(handle_detatched_state(git)?) } else { None };
+    let mut plan = Plan::transactional();
+    if let Some(new_branch) = &new_branch {
+        plan.run_with_undo(git_branch(new_branch), Undo::Run(git_delete_branch(new_branch, true)));
+        plan.run_with_undo(git_checkout(new_branch), Undo::Run(git_checkout(&git.current_commit()?)));
+    }
+    for manifest in manifests.into_iter().filter(|manifest| manifest.is_changed()) {
+        let contents = manifest.contents();
+        plan.write_file(manifest.path, Some(manifest.original), contents);
+    }
+    if !options.changelog.unwrap_or(true) {
+        skipped.push(format!("update {} (disabled)", CHANGELOG_FILE));
+    } else {
+        let date = today();
+        for saved_crate in saved_crates.iter() {
+            let changelog_path = saved_crate.dir.join(CHANGELOG_FILE);
+            let original = fs::read_to_string(&changelog_path).ok();
+            let log_path = saved_crate.dir.join(".");
+            let (_, notes) = collect_release_notes(git, &saved_crate.tag_format, None, &log_path.to_string_lossy(), options.commit_message.as_deref())?;
+            let contents = insert_release_section(original.as_deref(), &saved_crate.new_version, &date, &notes);
+            plan.write_file(changelog_path, original, contents);
+        }
+    }
+    let lockfile_dir = match find_workspace_root_manifest(Path::new(CARGO_MANIFEST))? {
+        Some(root_manifest_path) => root_manifest_path.parent().map(|dir| dir.to_path_buf()).unwrap_or_default(),
+        None => PathBuf::new()
+    };
+    plan.run_with_undo(cargo_generate_lockfile(), Undo::restore_file(lockfile_dir.join(CARGO_LOCKFILE)));
+    plan.run_with_undo(git_stage_all_changes(), Undo::Run(git_unstage_all_changes()));
+    let commit_message = options.commit_message.as_deref().unwrap_or(DEFAULT_MSG);
+    plan.run_with_undo(git_commit_with_message(commit_message), Undo::Run(git_reset_last_commit(options.rollback_mode.unwrap_or(ResetMode::Soft))));
+    let sign = options.sign.unwrap_or(false);
+    for saved_crate in saved_crates.iter() {
+        let tag_command = if sign || options.annotate.unwrap_or(false) {
+            git_create_annotated_tag(&saved_crate.tag, commit_message, sign)
+        } else {
+            git_create_tag(&saved_crate.tag)
+        };
+        plan.run_with_undo(tag_command, Undo::Run(git_delete_tag(&saved_crate.tag)));
+    }
+    let remote_name = match &options.remote {
+        Some(remote_name) => Some(remote_name.clone()),
+        None => git.remote_name()?
+    };
+    match &remote_name {
+        Some(remote_name) => {
+            let branch_name = match &new_branch {
+                Some(new_branch) => new_branch.clone(),
+                None if is_detatched => String::from("<new branch>"),
+                None => git.branch_name()?
+            };
+            let push_target = match &options.branch {
+                Some(push_branch) if push_branch != &branch_name => format!("{}:{}", branch_name, push_branch),
+                _ => branch_name
+            };
+            plan.run_with_undo(git_push(remote_name, &push_target), Undo::Irreversible);
+        }
+        None => skipped.push(String::from("git push (no remote)"))
+    }
+    let publish_after_push = options.publish_after_push.unwrap_or(false);
+    for saved_crate in saved_crates.iter() {
+        let publish_command = match &saved_crate.name {
+            Some(name) => cargo_publish_package(name),
+            None => cargo_publish()
+        };
+        match (publish_after_push, saved_crate.publish) {
+            (true, true) => plan.run_with_undo(publish_command, Undo::Irreversible),
+            (true, false) => skipped.push(format!("{} (publish = false)", publish_command)),
+            (false, _) => skipped.push(format!("{} (not requested)", publish_command))
+        }
+    }
+    let mut new_versions_message = match saved_crates.as_slice() {
+        [SavedCrate { name: None, new_version, .. }] => format!(" New version: {}", new_version),
+        _ => {
+            let new_versions: Vec<String> = saved_crates.iter().map(|saved_crate| format!("{} {}", saved_crate.name.as_deref().unwrap_or_default(), saved_crate.new_version)).collect();
+            format!(" New versions: {}", new_versions.join(", "))
+        }
+    };
+    let sharing: Vec<&str> = saved_crates.iter().filter(|saved_crate| !saved_crate.selected).filter_map(|saved_crate| saved_crate.name.as_deref()).collect();
+    if !sharing.is_empty() {
+        new_versions_message.push_str(format!("\n(also saved, as they share the [workspace.package] version of a saved crate: {})", sharing.join(", ")).as_str());
+    }
+    for saved_crate in saved_crates.iter() {
+        match (&saved_crate.name, &saved_crate.bump_reason) {
+            (Some(name), Some(reason)) => new_versions_message.push_str(format!("\n(auto bump of {}: {})", name, reason).as_str()),
+            (None, Some(reason)) => new_versions_message.push_str(format!("\n(auto bump: {})", reason).as_str()),
+            _ => {}
+        }
+    }
+    if options.dry_run {
+        let mut dry_run_message = format!("Dry run, nothing was changed!{}", new_versions_message);
+        if !checks.is_empty() {
+            dry_run_message.push_str("\nChecks that must pass before any change:");
+            for check in checks {
+                dry_run_message.push_str(format!("\n  - {}", check).as_str());
+            }
+        } else if options.skip_checks && !options.checks.is_empty() {
+            dry_run_message.push_str(format!("\n(Skipping {} configured checks)", options.checks.len()).as_str());
+        }
+        if is_detatched {
+            dry_run_message.push_str("\n(HEAD is detatched, save would first ask to create a new branch for these changes)");
+        }
+        let report = save_report(git, format!("{}\n{}", dry_run_message, plan), &saved_crates, remote_name.as_deref(), false, &skipped, true)?;
+        return Ok(report.field("steps_planned", Json::strs(plan.summaries())));
+    }
+    plan.execute().on_error("Save failed")?;
+    let mut final_message = match &new_branch {
+        Some(new_branch) => format!("Created new branch {} and switched to it!\n", new_branch),
+        None => String::new()
+    };
+    final_message.push_str("Saved, Committed, Tagged");
+    if remote_name.is_some() {
         final_message.push_str(", Pushed");
     }
-    if options.publish_after_push {
-        cargo_publish()?;
+    if publish_after_push {
         final_message.push_str(", Published");
     }
     final_message.push('!');
-    if let Some(new_version) = options.new_version {
-        final_message.push_str(format!(" New version: {}", new_version.to_string()).as_str());
-    }
-    return Ok(final_message)
+    final_message.push_str(&new_versions_message);
+    let published = publish_after_push && saved_crates.iter().any(|saved_crate| saved_crate.publish);
+    let report = save_report(git, final_message, &saved_crates, remote_name.as_deref(), published, &skipped, false)?;
+    return Ok(report.field("steps_done", Json::strs(plan.summaries())))
+}
+
+/// The fields of the JSON result of a save. The version and tag fields describe the single saved crate,
+/// and are `null` when several workspace members were saved (see `crates`)
+fn save_report(git: &dyn GitBackend, message: String, saved_crates: &[SavedCrate], remote_name: Option<&str>, published: bool, skipped: &[String], dry_run: bool) -> Result<Report, ErrorChain> {
+    let single = match saved_crates {
+        [saved_crate] => Some(saved_crate),
+        _ => None
+    };
+    let crates = saved_crates.iter().map(|saved_crate| Json::object(vec![
+        ("name", Json::opt_str(saved_crate.name.as_deref())),
+        ("old_version", Json::str(&saved_crate.old_version)),
+        ("new_version", Json::str(&saved_crate.new_version)),
+        ("tag", Json::str(&saved_crate.tag)),
+        ("bump_reason", Json::opt_str(saved_crate.bump_reason.as_deref())),
+        ("selected", Json::Bool(saved_crate.selected))
+    ])).collect();
+    let commit = if dry_run { None } else { Some(git.current_commit()?) };
+    let branch = git.branch_name()?;
+    return Ok(Report::new(message)
+        .field("dry_run", Json::Bool(dry_run))
+        .field("old_version", Json::opt_str(single.map(|saved_crate| &saved_crate.old_version)))
+        .field("new_version", Json::opt_str(single.map(|saved_crate| &saved_crate.new_version)))
+        .field("tag", Json::opt_str(single.map(|saved_crate| &saved_crate.tag)))
+        .field("crates", Json::Array(crates))
+        .field("commit", Json::opt_str(commit))
+        .field("branch", Json::opt_str(Some(branch).filter(|branch| branch != "HEAD")))
+        .field("remote", Json::opt_str(remote_name))
+        .field("pushed", Json::Bool(!dry_run && remote_name.is_some()))
+        .field("published", Json::Bool(published))
+        .field("steps_skipped", Json::strs(skipped)));
 }
 
 fn process_args<I>(args_iter: &mut I) -> Result<SaveModeOptions, ErrorChain>
@@ -73,12 +325,26 @@ where I: Iterator<Item = String> {
                 if options.update_part.is_some() {
                     return Err(ErrorChain::new(format!("version part update ('{}' or '{}' or '{}') set more than once", SAVE_MAJOR, SAVE_MINOR, SAVE_PATCH)));
                 }
-                options.update_part = match next_arg_lower.as_str() {
-                    SAVE_MAJOR => Some(VersionPart::Major),
-                    SAVE_MINOR => Some(VersionPart::Minor),
-                    SAVE_PATCH => Some(VersionPart::Patch),
-                    _ => Some(VersionPart::Patch) // Impossible
+                options.update_part = VersionPart::parse(&next_arg_lower);
+            }
+            SAVE_PRE | SAVE_ALPHA | SAVE_BETA | SAVE_RC => {
+                if options.pre_release_label.is_some() {
+                    return Err(ErrorChain::new(format!("pre-release label ('{} <label>' or '{}' or '{}' or '{}') set more than once", SAVE_PRE, SAVE_ALPHA, SAVE_BETA, SAVE_RC)));
+                }
+                let label = match next_arg_lower.as_str() {
+                    SAVE_PRE => args_iter.next().on_error("the 'pre' option must be followed by a pre-release label (eg. 'alpha')")?,
+                    label => label.to_owned()
                 };
+                if !is_valid_version_identifier(&label) || label.chars().all(|c| c.is_ascii_digit()) {
+                    return Err(ErrorChain::new(format!("invalid pre-release label '{}', must contain only [0-9A-Za-z-] and at least one non-digit", label)));
+                }
+                options.pre_release_label = Some(label);
+            }
+            SAVE_RELEASE => {
+                options.release = true;
+            }
+            SAVE_AUTO => {
+                options.auto = true;
             }
             SAVE_WITH_MSG => {
                 if options.commit_message.is_some() {
@@ -92,69 +358,317 @@ where I: Iterator<Item = String> {
                 }
             }
             SAVE_PUBLISH => {
-                options.publish_after_push = true;
+                options.publish_after_push = Some(true);
+            }
+            SAVE_NO_PUBLISH => {
+                options.publish_after_push = Some(false);
+            }
+            SAVE_REMOTE => {
+                options.remote = Some(args_iter.next().on_error(format!("the '{}' option must be followed by the name of a remote", SAVE_REMOTE))?);
+            }
+            SAVE_BRANCH => {
+                options.branch = Some(args_iter.next().on_error(format!("the '{}' option must be followed by the name of a branch to push to", SAVE_BRANCH))?);
+            }
+            SAVE_PACKAGE => {
+                let package = args_iter.next().on_error("the '-p' option must be followed by the name of a workspace member")?;
+                if !options.packages.contains(&package) {
+                    options.packages.push(package);
+                }
+            }
+            SAVE_ALL_PACKAGES => {
+                options.all_packages = true;
+            }
+            SAVE_DRY_RUN => {
+                options.dry_run = true;
+            }
+            SAVE_TAG_TEMPLATE => {
+                let template = args_iter.next().on_error(format!("the '{}' option must be followed by a template such as 'v{{version}}'", SAVE_TAG_TEMPLATE))?;
+                if !template.contains("{version}") {
+                    return Err(ErrorChain::new(format!("invalid tag template '{}', it must contain '{{version}}'", template)));
+                }
+                options.tag_template = Some(template);
+            }
+            SAVE_ANNOTATE => {
+                options.annotate = Some(true);
+            }
+            SAVE_LIGHTWEIGHT => {
+                options.annotate = Some(false);
+                options.sign = Some(false);
+            }
+            SAVE_SIGN => {
+                options.sign = Some(true);
+            }
+            SAVE_ROLLBACK => {
+                let mode = args_iter.next().on_error(format!("the '{}' option must be followed by '{}' or '{}'", SAVE_ROLLBACK, ROLLBACK_SOFT, ROLLBACK_HARD))?;
+                options.rollback_mode = Some(ResetMode::parse(&mode).do_on_error(|| format!("invalid rollback mode '{}', must be '{}' or '{}'", mode, ROLLBACK_SOFT, ROLLBACK_HARD))?);
+            }
+            SAVE_SKIP_CHECKS => {
+                options.skip_checks = true;
+            }
+            SAVE_NO_CHANGELOG => {
+                options.changelog = Some(false);
+            }
+            SAVE_SEMVER_CHECK => {
+                options.semver_check = Some(true);
+            }
+            SAVE_NO_SEMVER_CHECK => {
+                options.semver_check = Some(false);
+            }
+            SAVE_BUILD_METADATA => {
+                if options.build_metadata.is_some() {
+                    return Err(ErrorChain::new("build metadata argument passed more than once"));
+                }
+                let metadata = args_iter.next().on_error("the '-build' option must be followed by build metadata (eg. 'exp.sha.5114f85')")?;
+                if !metadata.split('.').all(is_valid_version_identifier) {
+                    return Err(ErrorChain::new(format!("invalid build metadata '{}', identifiers must contain only [0-9A-Za-z-]", metadata)));
+                }
+                options.build_metadata = Some(metadata);
             }
+            _ if take_output_format(&next_arg, args_iter)? => {}
             _ => return Err(ErrorChain::new(format!("invalid argument passed to save mode: '{}'", next_arg_lower)))
         }
     }
+    if options.all_packages && !options.packages.is_empty() {
+        return Err(ErrorChain::new(format!("'{}' cannot be combined with '{}'", SAVE_ALL_PACKAGES, SAVE_PACKAGE)));
+    }
+    if options.annotate == Some(false) && options.sign == Some(true) {
+        return Err(ErrorChain::new(format!("'{}' cannot be combined with '{}', signed tags are always annotated", SAVE_SIGN, SAVE_LIGHTWEIGHT)));
+    }
+    if options.release && (options.update_part.is_some() || options.pre_release_label.is_some() || options.auto) {
+        return Err(ErrorChain::new(format!("'{}' cannot be combined with a version part, '{}' or a pre-release label", SAVE_RELEASE, SAVE_AUTO)));
+    }
+    if options.auto && options.update_part.is_some() {
+        return Err(ErrorChain::new(format!("'{}' cannot be combined with '{}', '{}' or '{}'", SAVE_AUTO, SAVE_MAJOR, SAVE_MINOR, SAVE_PATCH)));
+    }
     return Ok(options);
 }
 
-fn handle_detatched_state() -> Result<(), ErrorChain> {
-    let original_branch = last_attatched_head_branch()?;
+/// Asks to move the changes of a detatched HEAD to a new branch, returning its name. The branch is created and
+/// checked out by the steps of the save, so a rollback removes it again
+fn handle_detatched_state(git: &dyn GitBackend) -> Result<String, ErrorChain> {
+    let original_branch = git.last_attatched_head_branch()?;
     let mut buffer = String::new();
-    print!(r#"Cannot save while in a detatched head state
+    print_message(r#"Cannot save while in a detatched head state
 (while checked out to a commit that isnt the latest in the branch)
 Would you like to create a new branch from these changes now? (y/n): "#);
     read_stdin_line(&mut buffer)?;
     if !cli_affirmative(buffer) {
         buffer = String::new();
-        print!(r#"Would you like to discard these changes instead?
+        print_message(r#"Would you like to discard these changes instead?
 (WARNING: this may be irreversible) (y/n): "#);
         read_stdin_line(&mut buffer)?;
         if !cli_affirmative(buffer) {
-            return Err(ErrorChain::new("Save aborted due to detatched head"))
+            return Err(aborted("Save aborted due to detatched head"))
         }
-        git_checkout(&original_branch)?;
-        return Err(ErrorChain::new("Save aborted due to detatched head, returned to original branch while discarding changes"));
+        git_checkout(&original_branch).run()?;
+        return Err(aborted("Save aborted due to detatched head, returned to original branch while discarding changes"));
     } else {
         buffer = String::new();
-        print!(r#"Name for the new branch: "#);
+        print_message("Name for the new branch: ");
         read_stdin_line(&mut buffer)?;
         let branch_name = buffer.trim().to_owned();
-        git_branch(&branch_name)?;
-        git_checkout(&branch_name)?;
-        print!("Created new branch {} and switched to it to it!", branch_name);
+        if branch_name.is_empty() {
+            return Err(aborted("Save aborted, the new branch needs a name"));
+        }
+        return Ok(branch_name);
+    }
+}
+
+/// Computes the new versions and the edited manifests, without writing anything yet
+fn process_cargo_changes(git: &dyn GitBackend, options: &SaveModeOptions) -> Result<(Vec<SavedCrate>, Vec<Manifest>), ErrorChain> {
+    let workspace = Workspace::discover(Path::new(CARGO_MANIFEST))?.filter(|workspace| workspace.has_multiple_members());
+    let workspace = match workspace {
+        Some(workspace) => workspace,
+        None => {
+            if options.all_packages || !options.packages.is_empty() {
+                return Err(ErrorChain::new(format!("'{}' and '{}' can only be used inside a workspace with multiple members", SAVE_PACKAGE, SAVE_ALL_PACKAGES)));
+            }
+            let (mut manifest, version_location) = load_version_manifest(Path::new(CARGO_MANIFEST))?;
+            let old_ver = manifest.version(version_location)?;
+            let tag_format = current_tag_format(options.tag_template.as_deref())?;
+            let (new_ver, bump_reason) = new_version(git, &[BumpSource::new(git, None, &tag_format, Path::new(""))?], &old_ver, options)?;
+            manifest.set_version(version_location, &new_ver)?;
+            let publish = Manifest::load(CARGO_MANIFEST)?.is_publishable();
+            let tag = tag_format.render(&new_ver);
+            return Ok((vec![SavedCrate { name: None, tag, tag_format, dir: PathBuf::new(), old_version: old_ver, new_version: new_ver, publish, bump_reason, selected: true }], vec![manifest]));
+        }
+    };
+    let tag_template = options.tag_template.as_deref().unwrap_or(DEFAULT_WORKSPACE_TAG_TEMPLATE);
+    let selected: Vec<String> = if options.all_packages {
+        workspace.members.iter().map(|member| member.name.clone()).collect()
+    } else if !options.packages.is_empty() {
+        for package in options.packages.iter() {
+            workspace.member(package).do_on_error(|| format!("'{}' is not a member of the workspace", package))?;
+        }
+        options.packages.clone()
+    } else {
+        let changed = workspace.changed_members(git, tag_template)?;
+        if changed.is_empty() {
+            return Err(ErrorChain::new(format!("no workspace member changed since its last version tag, use '{} <name>' or '{}' to choose crates to save", SAVE_PACKAGE, SAVE_ALL_PACKAGES)));
+        }
+        changed
+    };
+    let mut manifests: BTreeMap<PathBuf, Manifest> = BTreeMap::new();
+    for manifest_path in workspace.all_manifest_paths() {
+        manifests.insert(manifest_path.clone(), Manifest::load(&manifest_path)?);
+    }
+    let mut new_versions: Vec<(String, Version, Version, Option<String>)> = Vec::new();
+    let selected_sharing: Vec<&WorkspaceMember> = workspace.members.iter().filter(|member| selected.contains(&member.name) && member.inherits_version).collect();
+    if !selected_sharing.is_empty() {
+        let root_manifest = manifests.get_mut(&workspace.root_manifest_path).on_error("workspace root manifest was not loaded")?;
+        let old_ver = root_manifest.version(VersionLocation::WorkspacePackage)?;
+        let tag_formats: Vec<TagFormat> = selected_sharing.iter().map(|member| TagFormat::new(tag_template, &member.name)).collect();
+        let mut sources = Vec::new();
+        for (tag_format, member) in tag_formats.iter().zip(selected_sharing.iter()) {
+            sources.push(BumpSource::new(git, Some(&member.name), tag_format, member.dir())?);
+        }
+        let (new_ver, bump_reason) = new_version(git, &sources, &old_ver, options)?;
+        root_manifest.set_version(VersionLocation::WorkspacePackage, &new_ver)?;
+        for member in workspace.members.iter().filter(|member| member.inherits_version) {
+            new_versions.push((member.name.clone(), old_ver.clone(), new_ver.clone(), bump_reason.clone()));
+        }
+    }
+    for member in workspace.members.iter().filter(|member| selected.contains(&member.name) && !member.inherits_version) {
+        let manifest = manifests.get_mut(&member.manifest_path).on_error(format!("manifest of '{}' was not loaded", member.name))?;
+        let old_ver = manifest.version(VersionLocation::Package)?;
+        let tag_format = TagFormat::new(tag_template, &member.name);
+        let (new_ver, bump_reason) = new_version(git, &[BumpSource::new(git, Some(&member.name), &tag_format, member.dir())?], &old_ver, options)?;
+        manifest.set_version(VersionLocation::Package, &new_ver)?;
+        new_versions.push((member.name.clone(), old_ver, new_ver, bump_reason));
+    }
+    for (name, _, new_ver, _) in new_versions.iter() {
+        for manifest in manifests.values_mut() {
+            manifest.set_path_dependency_version(name, new_ver)?;
+        }
+    }
+    let bumped_names: Vec<String> = new_versions.iter().map(|(name, _, _, _)| name.clone()).collect();
+    let current_dir = env::current_dir().ok().and_then(|dir| fs::canonicalize(dir).ok()).unwrap_or_default();
+    let mut saved_crates = Vec::new();
+    for member in workspace.dependency_order(&bumped_names)? {
+        let (_, old_ver, new_ver, bump_reason) = new_versions.iter().find(|(name, _, _, _)| name == &member.name).on_error("bumped member missing its new version")?;
+        let tag_format = TagFormat::new(tag_template, &member.name);
+        saved_crates.push(SavedCrate {
+            name: Some(member.name.clone()),
+            tag: tag_format.render(new_ver),
+            tag_format,
+            dir: relative_dir(member.dir(), &current_dir),
+            old_version: old_ver.clone(),
+            new_version: new_ver.clone(),
+            publish: member.publish,
+            bump_reason: bump_reason.clone(),
+            selected: selected.contains(&member.name),
+        });
+    }
+    Ok((saved_crates, manifests.into_values().collect()))
+}
+
+/// A crate whose commits and public API decide a version bump (several for a shared `workspace.package` version)
+struct BumpSource<'a> {
+    /// `None` for a single crate rather than a workspace member
+    package: Option<&'a str>,
+    dir: &'a Path,
+    previous_tag: Option<String>,
+}
+
+impl<'a> BumpSource<'a> {
+    fn new(git: &dyn GitBackend, package: Option<&'a str>, tag_format: &TagFormat, dir: &'a Path) -> Result<BumpSource<'a>, ErrorChain> {
+        let previous_tag = find_previous_version_tag(git, tag_format)?.map(|(tag, _)| tag);
+        return Ok(BumpSource { package, dir, previous_tag });
+    }
+}
+
+/// The next version after `old_ver` and, with `auto`, why its part was chosen. Refuses a bump that is too small
+/// for the public API changes found by `cargo semver-checks` (pre-releases make no compatibility promise)
+fn new_version(git: &dyn GitBackend, sources: &[BumpSource], old_ver: &Version, options: &SaveModeOptions) -> Result<(Version, Option<String>), ErrorChain> {
+    let (mut update_part, mut bump_reason) = choose_update_part(git, sources, old_ver, options)?;
+    let required = required_update_part(sources, old_ver, options)?;
+    if let (true, Some((required_part, api_reason))) = (options.auto, &required) {
+        if required_part.significance() > update_part.unwrap_or(VersionPart::Patch).significance() {
+            update_part = Some(*required_part);
+            bump_reason = Some(format!("{}, raised to {} by the public API check ({})", bump_reason.unwrap_or_default(), required_part, api_reason));
+        }
+    }
+    let new_ver = bump_version(old_ver, update_part.as_ref(), options)?;
+    if let Some((required_part, api_reason)) = required {
+        let bumped_part = new_ver.bumped_part(old_ver);
+        if !old_ver.is_pre_release() && !new_ver.is_pre_release() && bumped_part.significance() < required_part.significance() {
+            return Err(ErrorChain::new(format!("the public API changes need a {} bump, but {} -> {} is a {} bump ({})\nuse 'save {}' or 'save {}', or '{}' to save anyway",
+                required_part, old_ver, new_ver, bumped_part, api_reason, required_part, SAVE_AUTO, SAVE_NO_SEMVER_CHECK)));
+        }
+    }
+    return Ok((new_ver, bump_reason));
+}
+
+/// The version part given on the command line or in the config, or with `auto` the part picked from
+/// the commits since the previous version tag of each source, and the new commit message
+fn choose_update_part(git: &dyn GitBackend, sources: &[BumpSource], old_ver: &Version, options: &SaveModeOptions) -> Result<(Option<VersionPart>, Option<String>), ErrorChain> {
+    if !options.auto {
+        return Ok((options.update_part, None));
+    }
+    let mut messages: Vec<String> = Vec::new();
+    for source in sources {
+        for message in git.commit_messages_since(source.previous_tag.as_deref(), &source.dir.join(".").to_string_lossy())? {
+            if !messages.contains(&message) {
+                messages.push(message);
+            }
+        }
     }
-    Ok(())
+    messages.extend(options.commit_message.clone());
+    let (update_part, reason) = choose_bump_part(&messages, old_ver);
+    let previous_tags: Vec<&str> = sources.iter().filter_map(|source| source.previous_tag.as_deref()).collect();
+    let since = if previous_tags.is_empty() { String::from("the first commit") } else { previous_tags.join(", ") };
+    return Ok((Some(update_part), Some(format!("{}, from the commits since {}", reason, since))));
 }
 
-fn process_cargo_changes(options: &mut SaveModeOptions) -> Result<(), ErrorChain> {
-    fs::metadata(CARGO_MANIFEST).on_error("No Cargo.toml file found! This command must be run from a valid Rust crate root directory")?;
-    let cargo_toml_str: String = fs::read_to_string(CARGO_MANIFEST).on_error("Cargo.toml could not be parsed to String")?;
-    let (cargo_toml_before_version, cargo_toml_version, cargo_toml_after_version) = split_version_from_cargo_toml(&cargo_toml_str)?;
-    let (major_ver, minor_ver, patch_ver) = split_parts_from_version(&cargo_toml_version)?;
-    let mut new_ver = Version{ major: 0, minor: 0, patch: 0 };
-    new_ver.major = major_ver.parse().on_error("Error parsing major version into u32")?;
-    new_ver.minor = minor_ver.parse().on_error("Error parsing minor version into u32")?;
-    new_ver.patch = patch_ver.parse().on_error("Error parsing patch version into u32")?;
-    match options.update_part.as_ref().unwrap_or(&VersionPart::Patch) {
-        VersionPart::Major => {
-            new_ver.major += 1;
-            new_ver.minor = 0;
-            new_ver.patch = 0;
-        },
-        VersionPart::Minor => {
-            new_ver.minor += 1;
-            new_ver.patch = 0;
-        }
-        VersionPart::Patch => {
-            new_ver.patch += 1;
-        }
-    }
-    let new_ver_string = new_ver.to_string();
-    options.new_version = Some(new_ver);
-    let new_cargo_toml_str = format!("{}{}{}", cargo_toml_before_version, new_ver_string, cargo_toml_after_version);
-    fs::write(CARGO_MANIFEST, new_cargo_toml_str).on_error("failed to write to Cargo.toml")?;
-    Ok(())
-}
\ No newline at end of file
+/// The smallest part allowed by the public API changes since the previous version tag of each source, with the reason
+fn required_update_part(sources: &[BumpSource], old_ver: &Version, options: &SaveModeOptions) -> Result<Option<(VersionPart, String)>, ErrorChain> {
+    let check_requested = match options.semver_check {
+        Some(false) => return Ok(None),
+        Some(true) => true,
+        None => false
+    };
+    if !semver_checks_installed() {
+        if check_requested {
+            return Err(ErrorChain::new(format!("the public API check needs cargo-semver-checks, install it with '{}' or use '{}'", SEMVER_CHECKS_INSTALL, SAVE_NO_SEMVER_CHECK)));
+        }
+        return Ok(None);
+    }
+    let mut required: Option<(VersionPart, String)> = None;
+    for source in sources {
+        let previous_tag = match &source.previous_tag {
+            Some(previous_tag) => previous_tag,
+            None => continue
+        };
+        let (change, summary) = public_api_change(source.package, previous_tag)?;
+        let part = change.for_version(old_ver);
+        if required.as_ref().map(|(required_part, _)| part.significance() > required_part.significance()).unwrap_or(true) {
+            required = Some((part, format!("{} since {}", summary, previous_tag)));
+        }
+    }
+    return Ok(required);
+}
+
+fn bump_version(old_ver: &Version, update_part: Option<&VersionPart>, options: &SaveModeOptions) -> Result<Version, ErrorChain> {
+    let mut new_ver = if options.release {
+        if !old_ver.is_pre_release() {
+            return Err(ErrorChain::new(format!("'{}' requires a pre-release version, but the current version is {}", SAVE_RELEASE, old_ver)));
+        }
+        old_ver.release()
+    } else if let Some(label) = &options.pre_release_label {
+        old_ver.next_pre_release(update_part, label)
+    } else {
+        old_ver.increment(update_part.unwrap_or(&VersionPart::Patch))
+    };
+    if let Some(metadata) = &options.build_metadata {
+        new_ver.build = metadata.split('.').map(|s| s.to_owned()).collect();
+    }
+    if &new_ver <= old_ver {
+        return Err(ErrorChain::new(format!("new version {} would not be greater than current version {}", new_ver, old_ver)));
+    }
+    Ok(new_ver)
+}
+
+/// `dir` relative to `base` when it is inside it, so planned steps show short paths
+fn relative_dir(dir: &Path, base: &Path) -> PathBuf {
+    return dir.strip_prefix(base).map(|relative| relative.to_path_buf()).unwrap_or(dir.to_path_buf());
+}
@@ -0,0 +1,219 @@
+use std::path::Path;
+
+use gmec::types::error_chain::{ErrorChain, ErrorPropogation};
+
+use crate::config::*;
+use crate::git::*;
+use crate::internal::*;
+use crate::manifest::*;
+use crate::registry::*;
+use crate::report::*;
+use crate::workspace::*;
+
+const STATUS_OFFLINE: &str = "--offline";
+
+/// Where one crate stands: its manifest version, its latest version tag and the registry
+struct CrateStatus {
+    /// `None` for a crate without a package name (never the case for a publishable one)
+    name: Option<String>,
+    version: Version,
+    /// The newest version tag in the history of HEAD, with the number of commits made after it
+    latest_tag: Option<(String, Version, usize)>,
+    publishable: bool,
+    /// `None` when the registry was not asked (`--offline` or `publish = false`)
+    published: Option<Published>
+}
+
+/// The remote save pushes to, with how far the checked out branch is from its copy there
+struct RemoteStatus {
+    name: String,
+    branch: String,
+    /// (ahead, behind), `None` when the branch was never pushed to the remote
+    ahead_behind: Option<(usize, usize)>
+}
+
+pub(crate) fn status_process<I>(mut args_iter: I) -> Result<Report, ErrorChain>
+where I: Iterator<Item = String> {
+    let mut offline = false;
+    while let Some(next_arg) = args_iter.next() {
+        match next_arg.as_str() {
+            STATUS_OFFLINE if !offline => offline = true,
+            _ if take_output_format(&next_arg, &mut args_iter)? => {}
+            _ => return Err(usage_error(format!("invalid argument passed to status mode: '{}'", next_arg)))
+        }
+    }
+    let git = open_git_backend()?;
+    let git = git.as_ref();
+    let config = load_config()?;
+    let crates = crate_statuses(git, config.tag_template.as_deref(), offline)?;
+    let commit = git.current_commit().on_error("could not read HEAD")?;
+    let detatched = git.is_detatched_mode().on_error("could not verify head attatchement")?;
+    let branch = if detatched {
+        git.last_attatched_head_branch().ok()
+    } else {
+        Some(git.branch_name().on_error("could not get branch name")?)
+    };
+    let changed_files = git.changed_files().on_error("could not read the status of the working tree")?;
+    let remote = match (git.remote_name()?, &branch) {
+        (Some(name), Some(branch)) if !detatched => {
+            let upstream = format!("{}/{}", name, branch);
+            let ahead_behind = if git.ref_exists(&upstream)? {
+                Some((git.count_commits(&upstream, "HEAD")?, git.count_commits("HEAD", &upstream)?))
+            } else {
+                None
+            };
+            Some(RemoteStatus { name, branch: branch.clone(), ahead_behind })
+        }
+        _ => None
+    };
+
+    let mut blockers = Vec::new();
+    if detatched {
+        blockers.push(String::from("HEAD is detatched, save would first ask to create a branch"));
+    }
+    if let Some(RemoteStatus { name, branch, ahead_behind: Some((_, behind @ 1..)) }) = &remote {
+        blockers.push(format!("{} commit(s) behind {}/{}, the push would be rejected until they are merged", behind, name, branch));
+    }
+    let unreleased = crates.iter().any(|crate_status| !matches!(crate_status.latest_tag, Some((_, _, 0))));
+    if !unreleased && changed_files.is_empty() {
+        blockers.push(String::from("nothing changed since the latest version tag"));
+    }
+
+    let mut output = String::new();
+    for crate_status in crates.iter() {
+        output.push_str(&crate_status.describe());
+        output.push('\n');
+    }
+    let head = match (detatched, &branch) {
+        (false, Some(branch)) => format!("HEAD: {} at {}", branch, short_hash(&commit)),
+        (true, Some(branch)) => format!("HEAD: detatched at {} (from {})", short_hash(&commit), branch),
+        (_, None) => format!("HEAD: detatched at {}", short_hash(&commit))
+    };
+    output.push_str(&head);
+    if changed_files.is_empty() {
+        output.push_str("\nWorking tree: clean");
+    } else {
+        output.push_str(format!("\nWorking tree: {} changed file(s), save commits them all:", changed_files.len()).as_str());
+        for file in changed_files.iter() {
+            output.push_str(format!("\n    {}", file).as_str());
+        }
+    }
+    output.push_str(match &remote {
+        Some(RemoteStatus { name, branch, ahead_behind: Some((ahead, behind)) }) => format!("\nRemote: {}/{}, {} ahead, {} behind", name, branch, ahead, behind),
+        Some(RemoteStatus { name, branch, ahead_behind: None }) => format!("\nRemote: {}, {} was never pushed to it", name, branch),
+        None if detatched => String::from("\nRemote: (not compared while HEAD is detatched)"),
+        None => String::from("\nRemote: none, save will not push")
+    }.as_str());
+    if blockers.is_empty() {
+        output.push_str("\nReady to save");
+    } else {
+        output.push_str("\nNot ready to save:");
+        for blocker in blockers.iter() {
+            output.push_str(format!("\n  - {}", blocker).as_str());
+        }
+    }
+
+    let single = match crates.as_slice() {
+        [crate_status] => Some(crate_status),
+        _ => None
+    };
+    let remote_json = match &remote {
+        Some(remote) => Json::object(vec![
+            ("name", Json::str(&remote.name)),
+            ("branch", Json::str(&remote.branch)),
+            ("ahead", remote.ahead_behind.map(|(ahead, _)| Json::Number(ahead as i64)).unwrap_or(Json::Null)),
+            ("behind", remote.ahead_behind.map(|(_, behind)| Json::Number(behind as i64)).unwrap_or(Json::Null))
+        ]),
+        None => Json::Null
+    };
+    return Ok(Report::new(output)
+        .field("version", Json::opt_str(single.map(|crate_status| &crate_status.version)))
+        .field("crates", Json::Array(crates.iter().map(|crate_status| crate_status.to_json()).collect()))
+        .field("commit", Json::str(&commit))
+        .field("branch", Json::opt_str(branch.as_deref()))
+        .field("detatched", Json::Bool(detatched))
+        .field("changed_files", Json::strs(changed_files.iter()))
+        .field("remote", remote_json)
+        .field("ready", Json::Bool(blockers.is_empty()))
+        .field("blockers", Json::strs(blockers.iter())));
+}
+
+/// The crates save would look at from the current directory: every member at the root of a workspace
+/// with several members, otherwise the current crate
+fn crate_statuses(git: &dyn GitBackend, tag_template: Option<&str>, offline: bool) -> Result<Vec<CrateStatus>, ErrorChain> {
+    let manifest_path = Path::new(CARGO_MANIFEST);
+    let manifest = Manifest::load(manifest_path)?;
+    let workspace = Workspace::discover(manifest_path)?.filter(|workspace| workspace.has_multiple_members() && manifest.is_workspace_root());
+    let mut crates = Vec::new();
+    match workspace {
+        Some(workspace) => {
+            let tag_template = tag_template.unwrap_or(DEFAULT_WORKSPACE_TAG_TEMPLATE);
+            for member in workspace.members.iter() {
+                crates.push(CrateStatus::read(git, &member.manifest_path, &TagFormat::new(tag_template, &member.name), offline)?);
+            }
+        }
+        None => crates.push(CrateStatus::read(git, manifest_path, &current_tag_format(tag_template)?, offline)?)
+    }
+    return Ok(crates);
+}
+
+impl CrateStatus {
+    fn read(git: &dyn GitBackend, manifest_path: &Path, tag_format: &TagFormat, offline: bool) -> Result<CrateStatus, ErrorChain> {
+        let manifest = Manifest::load(manifest_path)?;
+        let (version_manifest, version_location) = load_version_manifest(manifest_path)?;
+        let version = version_manifest.version(version_location)?;
+        let name = manifest.package_name().map(|name| name.to_owned());
+        let latest_tag = match find_previous_version_tag(git, tag_format)? {
+            Some((tag, tag_version)) => {
+                let commits_since = git.count_commits(&tag, "HEAD")?;
+                Some((tag, tag_version, commits_since))
+            }
+            None => None
+        };
+        let publishable = manifest.is_publishable();
+        let published = match &name {
+            Some(name) if publishable && !offline => Some(published_state(name, &version)),
+            _ => None
+        };
+        return Ok(CrateStatus { name, version, latest_tag, publishable, published });
+    }
+
+    fn describe(&self) -> String {
+        let mut description = match &self.name {
+            Some(name) => format!("{} {}", name, self.version),
+            None => format!("version {}", self.version)
+        };
+        description.push_str(match &self.latest_tag {
+            Some((tag, _, 0)) => format!("\n  latest version tag: {}, at HEAD", tag),
+            Some((tag, _, commits_since)) => format!("\n  latest version tag: {}, {} commit(s) since", tag, commits_since),
+            None => String::from("\n  latest version tag: none yet")
+        }.as_str());
+        description.push_str(match (&self.published, self.publishable) {
+            (_, false) => String::from("\n  registry: not published (publish = false)"),
+            (None, true) => format!("\n  registry: not checked ({})", STATUS_OFFLINE),
+            (Some(Published::Yes { yanked: false }), true) => format!("\n  registry: {} is published", self.version),
+            (Some(Published::Yes { yanked: true }), true) => format!("\n  registry: {} is published, but yanked", self.version),
+            (Some(Published::No), true) => format!("\n  registry: {} is not published", self.version),
+            (Some(Published::Unknown(reason)), true) => format!("\n  registry: unknown, {}", reason)
+        }.as_str());
+        return description;
+    }
+
+    fn to_json(&self) -> Json {
+        let (published, yanked) = match &self.published {
+            Some(Published::Yes { yanked }) => (Json::Bool(true), Json::Bool(*yanked)),
+            Some(Published::No) => (Json::Bool(false), Json::Bool(false)),
+            Some(Published::Unknown(_)) | None => (Json::Null, Json::Null)
+        };
+        return Json::object(vec![
+            ("name", Json::opt_str(self.name.as_deref())),
+            ("version", Json::str(&self.version)),
+            ("latest_tag", Json::opt_str(self.latest_tag.as_ref().map(|(tag, _, _)| tag))),
+            ("latest_tag_version", Json::opt_str(self.latest_tag.as_ref().map(|(_, version, _)| version))),
+            ("commits_since_tag", self.latest_tag.as_ref().map(|(_, _, commits_since)| Json::Number(*commits_since as i64)).unwrap_or(Json::Null)),
+            ("publish", Json::Bool(self.publishable)),
+            ("published", published),
+            ("yanked", yanked)
+        ]);
+    }
+}
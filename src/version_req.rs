@@ -0,0 +1,176 @@
+use std::cmp::Ordering;
+
+use gmec::types::error_chain::{ErrorChain, ErrorPropogation};
+
+use crate::internal::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret
+}
+
+/// One comparator of a requirement, eg. `>=1.2` (missing parts are `None`)
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Comparator {
+    op: Op,
+    major: u32,
+    minor: Option<u32>,
+    patch: Option<u32>,
+    pre: Vec<PreReleaseIdentifier>
+}
+
+/// A version requirement in Cargo's syntax (`^1.2`, `~1.4.1`, `>=1.0, <2.0`, `1.*`), except that a bare
+/// version without an operator matches by prefix (`1.4` is `1.4.x`, like `=1.4`) instead of meaning `^1.4`
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) struct VersionReq {
+    comparators: Vec<Comparator>
+}
+
+impl VersionReq {
+    pub fn parse(requirement: &str) -> Result<VersionReq, ErrorChain> {
+        let mut comparators = Vec::new();
+        for comparator in requirement.split(',').map(|comparator| comparator.trim()) {
+            if comparator.is_empty() {
+                return Err(ErrorChain::new(format!("empty comparator in version requirement '{}'", requirement)));
+            }
+            if let Some(comparator) = Comparator::parse(comparator).on_error(format!("invalid version requirement '{}'", requirement))? {
+                comparators.push(comparator);
+            }
+        }
+        return Ok(VersionReq { comparators });
+    }
+
+    /// Pre-releases only match when a comparator names a pre-release of the same `major.minor.patch`,
+    /// so `<2.0` never picks `2.0.0-rc.1`
+    pub fn matches(&self, version: &Version) -> bool {
+        if !self.comparators.iter().all(|comparator| comparator.matches(version)) {
+            return false;
+        }
+        if !version.is_pre_release() {
+            return true;
+        }
+        return self.comparators.iter().any(|comparator| {
+            !comparator.pre.is_empty() && comparator.major == version.major && comparator.minor == Some(version.minor) && comparator.patch == Some(version.patch)
+        });
+    }
+}
+
+impl Comparator {
+    /// `None` for a lone wildcard (`*`), which matches every version
+    fn parse(comparator: &str) -> Result<Option<Comparator>, ErrorChain> {
+        let (op, version) = [(">=", Op::GreaterEq), ("<=", Op::LessEq), (">", Op::Greater), ("<", Op::Less), ("=", Op::Exact), ("~", Op::Tilde), ("^", Op::Caret)]
+            .into_iter()
+            .find_map(|(prefix, op)| comparator.strip_prefix(prefix).map(|version| (op, version)))
+            .unwrap_or((Op::Exact, comparator));
+        let version = version.trim();
+        let version = version.strip_prefix('v').unwrap_or(version);
+        let (numbers, pre) = match version.split_once('-') {
+            Some((numbers, pre)) => (numbers, Some(pre)),
+            None => (version, None)
+        };
+        let mut parts = Vec::new();
+        for part in numbers.split('.') {
+            match part {
+                "*" | "x" | "X" => parts.push(None),
+                number if parts.last() == Some(&None) => return Err(ErrorChain::new(format!("'{}' cannot follow a wildcard", number))),
+                number => parts.push(Some(parse_version_number(number, "version")?))
+            }
+        }
+        if parts.len() > 3 {
+            return Err(ErrorChain::new(format!("'{}' has more than three version numbers", version)));
+        }
+        let part = |index: usize| parts.get(index).copied().flatten();
+        let major = match part(0) {
+            Some(major) => major,
+            None if op == Op::Exact && parts.len() == 1 => return Ok(None),
+            None => return Err(ErrorChain::new(format!("'{}' needs at least a major version", comparator)))
+        };
+        let (minor, patch) = (part(1), part(2));
+        if op != Op::Exact && parts.contains(&None) {
+            return Err(ErrorChain::new(format!("wildcards cannot follow an operator in '{}'", comparator)));
+        }
+        let mut identifiers = Vec::new();
+        if let Some(pre) = pre {
+            if patch.is_none() {
+                return Err(ErrorChain::new(format!("a pre-release needs a full version in '{}'", comparator)));
+            }
+            for identifier in pre.split('.') {
+                identifiers.push(PreReleaseIdentifier::parse(identifier)?);
+            }
+        }
+        return Ok(Some(Comparator { op, major, minor, patch, pre: identifiers }));
+    }
+
+    fn matches(&self, version: &Version) -> bool {
+        let compared = self.compare(version);
+        match self.op {
+            Op::Exact => compared == Ordering::Equal,
+            Op::Greater => compared == Ordering::Greater,
+            Op::GreaterEq => compared != Ordering::Less,
+            Op::Less => compared == Ordering::Less,
+            Op::LessEq => compared != Ordering::Greater,
+            Op::Tilde => match self.minor {
+                Some(minor) => compared != Ordering::Less && version.major == self.major && version.minor == minor,
+                None => version.major == self.major
+            },
+            Op::Caret => match (self.major, self.minor, self.patch) {
+                (_, None, _) => version.major == self.major,
+                (0, Some(0), Some(_)) => compared == Ordering::Equal,
+                (0, Some(minor), _) => compared != Ordering::Less && version.major == 0 && version.minor == minor,
+                (major, Some(_), _) => compared != Ordering::Less && version.major == major
+            }
+        }
+    }
+
+    /// Compares `version` with this comparator's version on the parts it names, so `1.4.2` equals `1.4`
+    fn compare(&self, version: &Version) -> Ordering {
+        let mut ordering = version.major.cmp(&self.major);
+        if let Some(minor) = self.minor {
+            ordering = ordering.then(version.minor.cmp(&minor));
+        }
+        if let Some(patch) = self.patch {
+            ordering = ordering.then(version.patch.cmp(&patch)).then_with(|| match (version.pre.is_empty(), self.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => version.pre.cmp(&self.pre)
+            });
+        }
+        return ordering;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matching<'a>(requirement: &str, versions: &[&'a str]) -> Vec<&'a str> {
+        let requirement = VersionReq::parse(requirement).unwrap();
+        return versions.iter().copied().filter(|version| requirement.matches(&Version::parse(version).unwrap())).collect();
+    }
+
+    #[test]
+    fn test_version_req_matches() {
+        let versions = ["0.2.3", "0.3.0", "1.3.9", "1.4.0", "1.4.2", "1.5.0", "2.0.0-rc.1", "2.0.0", "2.1.0"];
+        assert_eq!(matching("1.4", &versions), vec!["1.4.0", "1.4.2"]);
+        assert_eq!(matching("v1.4.*", &versions), vec!["1.4.0", "1.4.2"]);
+        assert_eq!(matching("1.4.2", &versions), vec!["1.4.2"]);
+        assert_eq!(matching("<2.0", &versions), vec!["0.2.3", "0.3.0", "1.3.9", "1.4.0", "1.4.2", "1.5.0"]);
+        assert_eq!(matching("^1.4", &versions), vec!["1.4.0", "1.4.2", "1.5.0"]);
+        assert_eq!(matching("^0.2", &versions), vec!["0.2.3"]);
+        assert_eq!(matching("~1.4.1", &versions), vec!["1.4.2"]);
+        assert_eq!(matching(">=1.4, <2", &versions), vec!["1.4.0", "1.4.2", "1.5.0"]);
+        assert_eq!(matching(">1", &versions), vec!["2.0.0", "2.1.0"]);
+        assert_eq!(matching(">=2.0.0-rc.1", &versions), vec!["2.0.0-rc.1", "2.0.0", "2.1.0"]);
+        assert_eq!(matching("*", &versions).len(), 8);
+        assert!(VersionReq::parse("main").is_err());
+        assert!(VersionReq::parse("1.*.3").is_err());
+        assert!(VersionReq::parse(">=1.4,").is_err());
+    }
+}
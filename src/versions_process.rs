@@ -0,0 +1,287 @@
+use gmec::types::error_chain::{ErrorChain, ErrorPropogation};
+
+use crate::config::*;
+use crate::date::*;
+use crate::git::*;
+use crate::internal::*;
+use crate::report::*;
+use crate::workspace::*;
+
+const VERSIONS_GRAPH: &str = "--graph";
+const VERSIONS_ALL_PARENTS: &str = "--all-parents";
+
+/// A commit of the branch with a version tag
+struct Release {
+    tag: String,
+    version: Version,
+    commit: String,
+    /// Position of the commit in the branch history, newest first: 0 is the tip of the branch
+    position: usize,
+    /// Commits between this release and the one before it in the listed walk, or up to and including it for the
+    /// oldest release. `None` when merged branches are followed, as their date order gives no meaningful count
+    commits_since_previous: Option<usize>
+}
+
+/// The releases of a branch, newest first, with where its tip and HEAD sit among them
+struct Timeline {
+    releases: Vec<Release>,
+    tip: String,
+    /// Position of HEAD in the branch history, `None` when the walk never reached it
+    head_position: Option<usize>,
+    /// Number of commits in the branch history
+    length: usize,
+    /// Whether the walk only followed first parents, the only one whose positions count the commits between two
+    /// of them (the steps of 'load prev')
+    first_parent: bool
+}
+
+impl Timeline {
+    /// Walks `commits` (a branch history, newest first) once, keeping the commits with a version tag
+    fn read<I>(commits: I, parents: Parents, tag_format: &TagFormat, head: &str) -> Timeline
+    where I: Iterator<Item = (String, Vec<String>)> {
+        let mut releases: Vec<Release> = Vec::new();
+        let mut tip = String::new();
+        let mut head_position = None;
+        let mut length = 0;
+        for (position, (commit, tags)) in commits.enumerate() {
+            if position == 0 {
+                tip = commit.clone();
+            }
+            if commit == head {
+                head_position = Some(position);
+            }
+            if let Some((tag, version)) = tag_format.find_version(&tags) {
+                releases.push(Release { tag: tag.to_owned(), version, commit: commit.clone(), position, commits_since_previous: None });
+            }
+            length = position + 1;
+        }
+        let mut timeline = Timeline { releases, tip, head_position, length, first_parent: parents == Parents::First };
+        for index in 0..timeline.releases.len() {
+            let older = timeline.releases.get(index + 1).map(|older| older.position).unwrap_or(length);
+            timeline.releases[index].commits_since_previous = timeline.commits_between(timeline.releases[index].position, older);
+        }
+        return timeline;
+    }
+
+    /// Commits from the one at `newer` (included) to the one at `older` (excluded) in the walk, `None` unless it
+    /// only followed first parents
+    fn commits_between(&self, newer: usize, older: usize) -> Option<usize> {
+        return Some(older - newer).filter(|_| self.first_parent);
+    }
+
+    /// The newest release at or before HEAD, with the number of commits HEAD is ahead of it
+    fn head_release(&self) -> Option<(&Release, Option<usize>)> {
+        let head_position = self.head_position?;
+        let release = self.releases.iter().find(|release| release.position >= head_position)?;
+        return Some((release, self.commits_between(head_position, release.position)));
+    }
+}
+
+pub(crate) fn versions_process<I>(mut args_iter: I) -> Result<Report, ErrorChain>
+where I: Iterator<Item = String> {
+    let mut graph = false;
+    let mut parents = Parents::First;
+    let mut branch = None;
+    while let Some(next_arg) = args_iter.next() {
+        match next_arg.as_str() {
+            VERSIONS_GRAPH => graph = true,
+            VERSIONS_ALL_PARENTS => parents = Parents::All,
+            _ if take_output_format(&next_arg, &mut args_iter)? => {}
+            _ if next_arg.starts_with('-') || branch.is_some() => {
+                return Err(usage_error(format!("invalid argument passed to versions mode: '{}'", next_arg)));
+            }
+            _ => branch = Some(next_arg)
+        }
+    }
+    let git = open_git_backend()?;
+    let git = git.as_ref();
+    let tag_format = current_tag_format(load_config()?.tag_template.as_deref())?;
+    let branch = match branch {
+        Some(branch) => branch,
+        None => current_or_last_branch(git)?
+    };
+    let head = git.current_commit().on_error("could not read HEAD")?;
+    let timeline = Timeline::read(get_reverse_chron_iterator_over_commits_in_branch(git, &branch, parents)?, parents, &tag_format, &head);
+
+    let mut summaries = Vec::new();
+    for release in timeline.releases.iter() {
+        summaries.push(git.commit_summary(&release.commit).on_error(format!("could not read the commit of {}", release.tag))?);
+    }
+    let mut output = if timeline.releases.is_empty() {
+        format!("No version tags on {} (tags are matched with the template '{}')", branch, tag_format.template)
+    } else if graph {
+        describe_graph(git, &timeline, &summaries, &branch, &head)?
+    } else {
+        describe_list(&timeline, &summaries, &branch)
+    };
+    output.push('\n');
+    output.push_str(&describe_head(&timeline, &branch, &head, parents));
+
+    let releases_json = timeline.releases.iter().zip(summaries.iter()).zip(timeline.releases.iter().skip(1).map(Some).chain([None]))
+        .map(|((release, (author, time, subject)), previous)| Json::object(vec![
+            ("tag", Json::str(&release.tag)),
+            ("version", Json::str(&release.version)),
+            ("commit", Json::str(&release.commit)),
+            ("date", Json::str(format_timestamp(*time))),
+            ("timestamp", Json::Number(*time)),
+            ("author", Json::str(author)),
+            ("subject", Json::str(subject)),
+            ("previous", Json::opt_str(previous.map(|previous| &previous.tag))),
+            ("commits_since_previous", release.commits_since_previous.map(|commits| Json::Number(commits as i64)).unwrap_or(Json::Null))
+        ]))
+        .collect();
+    let head_release = timeline.head_release();
+    return Ok(Report::new(output)
+        .field("branch", Json::str(&branch))
+        .field("tip", Json::str(&timeline.tip))
+        .field("head", Json::str(&head))
+        .field("head_in_branch", Json::Bool(timeline.head_position.is_some()))
+        .field("commits_after_latest", timeline.releases.first().and_then(|latest| timeline.commits_between(0, latest.position)).map(|commits| Json::Number(commits as i64)).unwrap_or(Json::Null))
+        .field("head_release", Json::opt_str(head_release.map(|(release, _)| &release.tag)))
+        .field("commits_after_head_release", head_release.and_then(|(_, ahead)| ahead).map(|ahead| Json::Number(ahead as i64)).unwrap_or(Json::Null))
+        .field("releases", Json::Array(releases_json)));
+}
+
+/// One line per release, newest first, with the number of commits since the release before it
+fn describe_list(timeline: &Timeline, summaries: &[(String, i64, String)], branch: &str) -> String {
+    let tag_width = timeline.releases.iter().map(|release| release.tag.len()).max().unwrap_or_default();
+    let author_width = summaries.iter().map(|(author, _, _)| author.chars().count()).max().unwrap_or_default();
+    let mut output = format!("Releases on {}, newest first:", branch);
+    for (index, (release, (author, time, subject))) in timeline.releases.iter().zip(summaries.iter()).enumerate() {
+        let since = match (timeline.releases.get(index + 1), release.commits_since_previous) {
+            (Some(previous), Some(commits)) => format!("{} commit(s) since {}", commits, previous.tag),
+            (Some(previous), None) => format!("after {}", previous.tag),
+            (None, Some(commits)) => format!("{} commit(s), first release", commits),
+            (None, None) => String::from("first release")
+        };
+        output.push_str(format!("\n  {:<tag_width$}  {}  {}  {:<author_width$}  {}  ({})", release.tag, format_date(*time), short_hash(&release.commit), author, subject, since).as_str());
+    }
+    let latest = &timeline.releases[0];
+    output.push_str(match (latest.position, timeline.commits_between(0, latest.position)) {
+        (0, _) => format!("\nThe tip of {} is {}", branch, latest.tag),
+        (_, Some(ahead)) => format!("\n{} commit(s) on {} after {}", ahead, branch, latest.tag),
+        (_, None) => format!("\nThe tip of {} is after {}", branch, latest.tag)
+    }.as_str());
+    return output;
+}
+
+/// The tip of the branch, HEAD and every release, newest first, with the number of commits between each of them:
+/// `*` marks the tip, `o` a release and `@` HEAD
+fn describe_graph(git: &dyn GitBackend, timeline: &Timeline, summaries: &[(String, i64, String)], branch: &str, head: &str) -> Result<String, ErrorChain> {
+    let mut positions: Vec<usize> = timeline.releases.iter().map(|release| release.position).chain([0]).chain(timeline.head_position).collect();
+    positions.sort_unstable();
+    positions.dedup();
+    let mut rows = Vec::new();
+    for position in positions.iter().copied() {
+        let is_head = timeline.head_position == Some(position);
+        let release_index = timeline.releases.iter().position(|release| release.position == position);
+        let (mut name, commit, summary) = match release_index {
+            Some(index) => (timeline.releases[index].tag.clone(), timeline.releases[index].commit.clone(), summaries[index].clone()),
+            None => {
+                let (name, commit) = if position == 0 { (branch, timeline.tip.as_str()) } else { ("HEAD", head) };
+                (name.to_owned(), commit.to_owned(), git.commit_summary(commit).on_error(format!("could not read commit {}", commit))?)
+            }
+        };
+        let mut marks = Vec::new();
+        if is_head && (release_index.is_some() || position == 0) {
+            marks.push("HEAD");
+        }
+        if position == 0 && release_index.is_some() {
+            marks.push(branch);
+        }
+        if !marks.is_empty() {
+            name.push_str(format!(" ({})", marks.join(", ")).as_str());
+        }
+        let symbol = if is_head { '@' } else if release_index.is_some() { 'o' } else { '*' };
+        rows.push((position, symbol, name, commit, summary));
+    }
+    let name_width = rows.iter().map(|(_, _, name, _, _)| name.len()).max().unwrap_or_default();
+    let author_width = rows.iter().map(|(_, _, _, _, (author, _, _))| author.chars().count()).max().unwrap_or_default();
+    let mut lines = Vec::new();
+    for (index, (position, symbol, name, commit, (author, time, subject))) in rows.iter().enumerate() {
+        if index > 0 {
+            lines.push(match timeline.commits_between(rows[index - 1].0, *position) {
+                Some(commits) => format!("|   {} commit(s)", commits),
+                None => String::from("|")
+            });
+        }
+        lines.push(format!("{} {:<name_width$}  {}  {}  {:<author_width$}  {}", symbol, name, format_date(*time), short_hash(commit), author, subject));
+    }
+    if let Some((oldest, ..)) = rows.last() {
+        match timeline.commits_between(oldest + 1, timeline.length) {
+            Some(0) => {}
+            Some(earlier) => lines.push(format!("~   {} earlier commit(s)", earlier)),
+            None if oldest + 1 < timeline.length => lines.push(String::from("~   earlier commits")),
+            None => {}
+        }
+    }
+    return Ok(lines.join("\n"));
+}
+
+/// Where HEAD is relative to the releases, to pick what to give `load`
+fn describe_head(timeline: &Timeline, branch: &str, head: &str, parents: Parents) -> String {
+    let Some(head_position) = timeline.head_position else {
+        let hint = match parents {
+            Parents::First => format!(", or only on a merged branch ('{}' follows every parent)", VERSIONS_ALL_PARENTS),
+            Parents::All => String::new()
+        };
+        return format!("HEAD ({}) is not in the history of {}{}", short_hash(head), branch, hint);
+    };
+    let at_tip = match (head_position, timeline.commits_between(0, head_position)) {
+        (0, _) => format!(", at the tip of {}", branch),
+        (_, Some(behind)) => format!(", {} commit(s) behind the tip of {}", behind, branch),
+        (_, None) => format!(", behind the tip of {}", branch)
+    };
+    return match timeline.head_release() {
+        Some((release, _)) if release.position == head_position => format!("HEAD is at {}{}", release.tag, at_tip),
+        Some((release, Some(ahead))) => format!("HEAD is {} commit(s) after {}{}", ahead, release.tag, at_tip),
+        Some((release, None)) => format!("HEAD is after {}{}", release.tag, at_tip),
+        None if timeline.releases.is_empty() => format!("HEAD is at {}{}", short_hash(head), at_tip),
+        None => format!("HEAD is before the first release{}", at_tip)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeline() {
+        let mut git = FakeGit::new("main", &[
+            ("c5", &[], "fix: five"),
+            ("c4", &["v1.1.0", "latest"], "feat: four"),
+            ("c3", &[], "fix: three"),
+            ("c2", &["v1.0.1"], "fix: two"),
+            ("c1", &[], "feat: one"),
+            ("c0", &["v1.0.0"], "init"),
+        ]);
+        git.head = 2;
+        let tag_format = TagFormat::new("v{version}", "foo");
+        let commits = get_reverse_chron_iterator_over_commits_in_branch(&git, "main", Parents::First).unwrap();
+        let timeline = Timeline::read(commits, Parents::First, &tag_format, "c3");
+        let releases: Vec<(&str, usize, Option<usize>)> = timeline.releases.iter().map(|release| (release.tag.as_str(), release.position, release.commits_since_previous)).collect();
+        assert_eq!(releases, vec![("v1.1.0", 1, Some(2)), ("v1.0.1", 3, Some(2)), ("v1.0.0", 5, Some(1))]);
+        assert_eq!((timeline.tip.as_str(), timeline.head_position, timeline.length), ("c5", Some(2), 6));
+        assert_eq!(timeline.head_release().map(|(release, ahead)| (release.tag.as_str(), ahead)), Some(("v1.0.1", Some(1))));
+        assert_eq!(describe_head(&timeline, "main", "c3", Parents::First), "HEAD is 1 commit(s) after v1.0.1, 2 commit(s) behind the tip of main");
+
+        let graph = describe_graph(&git, &timeline, &[
+            (String::from("Test Author"), 5 * 86_400, String::from("feat: four")),
+            (String::from("Test Author"), 3 * 86_400, String::from("fix: two")),
+            (String::from("Test Author"), 86_400, String::from("init"))
+        ], "main", "c3").unwrap();
+        let markers: Vec<&str> = graph.lines().map(|line| if line.starts_with('|') { line } else { line.split("  ").next().unwrap_or_default() }).collect();
+        assert_eq!(markers, vec!["* main", "|   1 commit(s)", "o v1.1.0", "|   1 commit(s)", "@ HEAD", "|   1 commit(s)", "o v1.0.1", "|   2 commit(s)", "o v1.0.0"]);
+
+        let commits = get_reverse_chron_iterator_over_commits_in_branch(&git, "c1", Parents::First).unwrap();
+        let timeline = Timeline::read(commits, Parents::First, &tag_format, "c3");
+        assert_eq!(timeline.head_position, None);
+        assert_eq!(describe_head(&timeline, "c1", "c3", Parents::All), "HEAD (c3) is not in the history of c1");
+
+        // Following merged branches walks them by date, so positions no longer count the commits in between
+        let commits = get_reverse_chron_iterator_over_commits_in_branch(&git, "main", Parents::All).unwrap();
+        let timeline = Timeline::read(commits, Parents::All, &tag_format, "c3");
+        assert!(timeline.releases.iter().all(|release| release.commits_since_previous.is_none()));
+        assert_eq!(describe_head(&timeline, "main", "c3", Parents::All), "HEAD is after v1.0.1, behind the tip of main");
+    }
+}
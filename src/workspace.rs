@@ -0,0 +1,241 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use gmec::types::error_chain::{ErrorChain, ErrorPropogation};
+use toml_edit::DocumentMut;
+
+use crate::git::*;
+use crate::internal::*;
+use crate::manifest::*;
+
+/// A crate belonging to a Cargo workspace
+pub(crate) struct WorkspaceMember {
+    pub name: String,
+    pub manifest_path: PathBuf,
+    pub publish: bool,
+    pub inherits_version: bool,
+    /// Other workspace members this crate depends on through `path` (excluding dev-dependencies)
+    pub member_dependencies: Vec<String>
+}
+
+impl WorkspaceMember {
+    pub fn dir(&self) -> &Path {
+        return self.manifest_path.parent().unwrap_or(Path::new("."));
+    }
+}
+
+pub(crate) struct Workspace {
+    pub root_manifest_path: PathBuf,
+    pub members: Vec<WorkspaceMember>
+}
+
+impl Workspace {
+    /// Finds the workspace that `manifest_path` belongs to and reads all of its members
+    pub fn discover(manifest_path: &Path) -> Result<Option<Workspace>, ErrorChain> {
+        let root_manifest_path = match find_workspace_root_manifest(manifest_path)? {
+            Some(root_manifest_path) => root_manifest_path,
+            None => return Ok(None)
+        };
+        let root_manifest_str = fs::read_to_string(&root_manifest_path).on_error(format!("{} could not be parsed to String", root_manifest_path.display()))?;
+        let root_document = root_manifest_str.parse::<DocumentMut>().on_error(format!("{} is not a valid TOML document", root_manifest_path.display()))?;
+        let root_dir = root_manifest_path.parent().on_error("workspace root manifest has no parent directory")?.to_path_buf();
+        let workspace_table = root_document.get("workspace").and_then(|item| item.as_table_like()).on_error("no [workspace] table found in workspace root")?;
+        let string_list = |key: &str| -> Vec<String> {
+            workspace_table.get(key).and_then(|item| item.as_array())
+                .map(|array| array.iter().filter_map(|value| value.as_str().map(|s| s.to_owned())).collect())
+                .unwrap_or_default()
+        };
+        let mut member_dirs: Vec<PathBuf> = Vec::new();
+        if root_document.contains_key("package") {
+            member_dirs.push(root_dir.clone());
+        }
+        for pattern in string_list("members") {
+            for dir in expand_member_pattern(&root_dir, &pattern)? {
+                if dir.join(CARGO_MANIFEST).is_file() && !member_dirs.contains(&dir) {
+                    member_dirs.push(dir);
+                }
+            }
+        }
+        let excluded: Vec<PathBuf> = string_list("exclude").iter().filter_map(|exclude| fs::canonicalize(root_dir.join(exclude)).ok()).collect();
+        member_dirs.retain(|dir| !excluded.contains(dir));
+        let root_manifest = Manifest::load(&root_manifest_path)?;
+        let workspace_path_dependencies = root_manifest.workspace_path_dependencies();
+        let mut members = Vec::new();
+        for dir in member_dirs {
+            let member_manifest_path = dir.join(CARGO_MANIFEST);
+            let manifest = Manifest::load(&member_manifest_path)?;
+            let name = manifest.package_name().on_error(format!("no package name found in {}", member_manifest_path.display()))?.to_owned();
+            let member_dependencies = manifest.path_dependencies(&workspace_path_dependencies).into_iter()
+                .filter(|dependency| !dependency.dev_only)
+                .map(|dependency| dependency.package)
+                .collect();
+            members.push(WorkspaceMember {
+                name,
+                publish: manifest.is_publishable(),
+                inherits_version: manifest.version_location()? == VersionLocation::WorkspacePackage,
+                member_dependencies,
+                manifest_path: member_manifest_path,
+            });
+        }
+        let member_names: Vec<String> = members.iter().map(|member| member.name.clone()).collect();
+        for member in members.iter_mut() {
+            member.member_dependencies.retain(|dependency| member_names.contains(dependency));
+            member.member_dependencies.dedup();
+        }
+        return Ok(Some(Workspace { root_manifest_path, members }));
+    }
+
+    /// A workspace whose only member is its root package behaves exactly like a single crate
+    pub fn has_multiple_members(&self) -> bool {
+        return self.members.len() > 1;
+    }
+
+    pub fn member(&self, name: &str) -> Option<&WorkspaceMember> {
+        return self.members.iter().find(|member| member.name == name);
+    }
+
+    /// All manifests that may contain a path dependency on a member, root first
+    pub fn all_manifest_paths(&self) -> Vec<PathBuf> {
+        let mut paths = vec![self.root_manifest_path.clone()];
+        for member in self.members.iter() {
+            if !paths.contains(&member.manifest_path) {
+                paths.push(member.manifest_path.clone());
+            }
+        }
+        return paths;
+    }
+
+    /// Orders the named members so that every crate comes after the members it depends on
+    pub fn dependency_order(&self, names: &[String]) -> Result<Vec<&WorkspaceMember>, ErrorChain> {
+        let mut ordered: Vec<&WorkspaceMember> = Vec::new();
+        let mut remaining: Vec<&WorkspaceMember> = self.members.iter().filter(|member| names.contains(&member.name)).collect();
+        while !remaining.is_empty() {
+            let ready_index = remaining.iter().position(|member| {
+                member.member_dependencies.iter().all(|dependency| !names.contains(dependency) || ordered.iter().any(|done| &done.name == dependency))
+            });
+            match ready_index {
+                Some(index) => ordered.push(remaining.remove(index)),
+                None => {
+                    let cycle: Vec<&str> = remaining.iter().map(|member| member.name.as_str()).collect();
+                    return Err(ErrorChain::new(format!("dependency cycle between workspace members: {}", cycle.join(", "))));
+                }
+            }
+        }
+        return Ok(ordered);
+    }
+
+    /// Members with changes (committed, staged, unstaged or untracked) since the tag of their current version.
+    /// A member whose current version was never tagged counts as changed
+    pub fn changed_members(&self, git: &dyn GitBackend, tag_template: &str) -> Result<Vec<String>, ErrorChain> {
+        let mut changed = Vec::new();
+        for member in self.members.iter() {
+            let (manifest, location) = load_version_manifest(&member.manifest_path)?;
+            let tag = render_tag_template(tag_template, &member.name, &manifest.version(location)?);
+            let dir = member.dir().to_string_lossy().to_string();
+            if !git.tag_exists(&tag)? || git.has_changes_since(&tag, &dir)? {
+                changed.push(member.name.clone());
+            }
+        }
+        return Ok(changed);
+    }
+}
+
+/// The tag format of the crate in the current directory: the given template, or `{version}` for a
+/// single crate and `{crate}-v{version}` inside a workspace with multiple members
+pub(crate) fn current_tag_format(tag_template: Option<&str>) -> Result<TagFormat, ErrorChain> {
+    let manifest_path = Path::new(CARGO_MANIFEST);
+    if !manifest_path.is_file() {
+        return Ok(TagFormat::new(tag_template.unwrap_or(DEFAULT_TAG_TEMPLATE), ""));
+    }
+    let crate_name = Manifest::load(manifest_path)?.package_name().unwrap_or_default().to_owned();
+    let default_template = match Workspace::discover(manifest_path)? {
+        Some(workspace) if workspace.has_multiple_members() => DEFAULT_WORKSPACE_TAG_TEMPLATE,
+        _ => DEFAULT_TAG_TEMPLATE
+    };
+    return Ok(TagFormat::new(tag_template.unwrap_or(default_template), &crate_name));
+}
+
+/// Expands a `[workspace] members` entry, supporting `*` and `?` wildcards within path segments
+fn expand_member_pattern(root_dir: &Path, pattern: &str) -> Result<Vec<PathBuf>, ErrorChain> {
+    let mut candidates = vec![root_dir.to_path_buf()];
+    for segment in pattern.split('/').filter(|segment| !segment.is_empty() && *segment != ".") {
+        let mut next_candidates = Vec::new();
+        for candidate in candidates {
+            if !segment.contains(['*', '?']) {
+                next_candidates.push(candidate.join(segment));
+                continue;
+            }
+            let entries = match fs::read_dir(&candidate) {
+                Ok(entries) => entries,
+                Err(_) => continue
+            };
+            for entry in entries {
+                let entry = entry.on_error(format!("could not read directory {}", candidate.display()))?;
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                if entry.path().is_dir() && wildcard_match(segment, &file_name) {
+                    next_candidates.push(entry.path());
+                }
+            }
+        }
+        candidates = next_candidates;
+    }
+    let mut dirs: Vec<PathBuf> = candidates.into_iter().filter_map(|dir| fs::canonicalize(dir).ok()).collect();
+    dirs.sort();
+    return Ok(dirs);
+}
+
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    return pattern[p..].iter().all(|c| *c == '*');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*", "foo"));
+        assert!(wildcard_match("foo-*", "foo-core"));
+        assert!(wildcard_match("f?o*x", "fooox"));
+        assert!(!wildcard_match("foo-*", "bar-core"));
+        assert!(!wildcard_match("f?o", "fo"));
+    }
+
+    #[test]
+    fn test_dependency_order() {
+        let member = |name: &str, dependencies: &[&str]| WorkspaceMember {
+            name: name.to_owned(),
+            manifest_path: PathBuf::from(name).join(CARGO_MANIFEST),
+            publish: true,
+            inherits_version: false,
+            member_dependencies: dependencies.iter().map(|s| s.to_string()).collect()
+        };
+        let workspace = Workspace {
+            root_manifest_path: PathBuf::from(CARGO_MANIFEST),
+            members: vec![member("app", &["core", "macros"]), member("macros", &["core"]), member("core", &[])]
+        };
+        let all: Vec<String> = vec!["app".to_owned(), "macros".to_owned(), "core".to_owned()];
+        let order: Vec<&str> = workspace.dependency_order(&all).unwrap().iter().map(|member| member.name.as_str()).collect();
+        assert_eq!(order, vec!["core", "macros", "app"]);
+        let partial: Vec<&str> = workspace.dependency_order(&["app".to_owned()]).unwrap().iter().map(|member| member.name.as_str()).collect();
+        assert_eq!(partial, vec!["app"]);
+    }
+}
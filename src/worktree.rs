@@ -0,0 +1,178 @@
+use std::{env, fs, path::{Path, PathBuf}};
+
+use gmec::types::error_chain::{ErrorChain, ErrorPropogation};
+
+use crate::git::*;
+use crate::internal::*;
+use crate::plan::*;
+use crate::report::*;
+
+/// Lists the worktrees created by `load --worktree`, one `<path>\t<id>` per line, in the git directory
+/// shared by every worktree so each of them sees the same list
+const WORKTREE_REGISTRY: &str = "cargit-worktrees";
+
+/// A worktree created by `load --worktree`, with the ref checked out in it
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct LoadWorktree {
+    pub path: PathBuf,
+    pub id: String
+}
+
+fn registry_path(git: &dyn GitBackend) -> Result<PathBuf, ErrorChain> {
+    return Ok(git.common_dir().on_error("could not locate the git directory")?.join(WORKTREE_REGISTRY));
+}
+
+fn parse_registry(registry: &str) -> Vec<LoadWorktree> {
+    return registry.lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(path, id)| LoadWorktree { path: PathBuf::from(path), id: id.to_owned() })
+        .collect();
+}
+
+fn render_registry(worktrees: &[LoadWorktree]) -> String {
+    return worktrees.iter().map(|worktree| format!("{}\t{}\n", worktree.path.display(), worktree.id)).collect();
+}
+
+fn read_registry(git: &dyn GitBackend) -> Result<(PathBuf, Option<String>), ErrorChain> {
+    let registry_path = registry_path(git)?;
+    let registry = fs::read_to_string(&registry_path).ok();
+    return Ok((registry_path, registry));
+}
+
+/// The default worktree for `id`: a sibling of the working tree named after both, eg. `../cargit-v1.4.2`
+pub(crate) fn default_worktree_path(work_dir: &Path, id: &str) -> PathBuf {
+    let id = if id.len() == 40 && id.chars().all(|c| c.is_ascii_hexdigit()) { &id[..12] } else { id };
+    let id: String = id.chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-' { c } else { '-' }).collect();
+    let name = work_dir.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    return work_dir.with_file_name(format!("{}-{}", name, id));
+}
+
+/// Plans a new worktree at `path` (or the default one) with `id` checked out, its entry in the list of
+/// worktrees load created and, if `build` is set, a `cargo build` of the current crate inside it.
+/// Returns the plan and the path of the worktree
+pub(crate) fn plan_load_worktree(git: &dyn GitBackend, id: &str, path: Option<&str>, build: bool) -> Result<(Plan, PathBuf), ErrorChain> {
+    let work_dir = git.work_dir().on_error("could not locate the working tree")?;
+    let path = match path {
+        Some(path) => env::current_dir().on_error("could not read the current directory")?.join(path),
+        None => default_worktree_path(&work_dir, id)
+    };
+    let path = match (path.parent().and_then(|parent| fs::canonicalize(parent).ok()), path.file_name()) {
+        (Some(parent), Some(name)) => parent.join(name),
+        _ => path
+    };
+    if path.exists() {
+        return Err(ErrorChain::new(format!("{} already exists, pass another path with '--worktree=<path>'", path.display())));
+    }
+    let (registry_path, registry) = read_registry(git)?;
+    let mut worktrees = parse_registry(registry.as_deref().unwrap_or_default());
+    worktrees.push(LoadWorktree { path: path.clone(), id: id.to_owned() });
+    let mut plan = Plan::new();
+    plan.run(git_worktree_add(&path, id));
+    plan.write_file(registry_path, registry, render_registry(&worktrees));
+    if build {
+        let current_dir = env::current_dir().on_error("could not read the current directory")?;
+        let crate_dir = fs::canonicalize(&current_dir).unwrap_or(current_dir);
+        let crate_dir = crate_dir.strip_prefix(fs::canonicalize(&work_dir).unwrap_or(work_dir)).map(|dir| dir.to_path_buf()).unwrap_or_default();
+        plan.run(cargo_build_manifest(&path.join(crate_dir).join(CARGO_MANIFEST)));
+    }
+    return Ok((plan, path));
+}
+
+/// Lists the worktrees load created, marking those git no longer knows about. Returns the list as text and as
+/// JSON (`path`, `id` and `missing` for each worktree)
+pub(crate) fn list_load_worktrees(git: &dyn GitBackend) -> Result<(String, Json), ErrorChain> {
+    let (_, registry) = read_registry(git)?;
+    let worktrees = parse_registry(registry.as_deref().unwrap_or_default());
+    if worktrees.is_empty() {
+        return Ok((String::from("No worktrees created by 'cargit load --worktree'"), Json::Array(Vec::new())));
+    }
+    let linked_worktrees = canonical_paths(git.linked_worktrees().on_error("could not list worktrees")?);
+    let mut output = String::from("Worktrees created by 'cargit load --worktree':");
+    let mut listed = Vec::new();
+    for worktree in worktrees {
+        let linked = is_linked(&linked_worktrees, &worktree.path);
+        let missing = if linked { "" } else { " (missing, removed by --prune)" };
+        output.push_str(format!("\n  {}  {}{}", worktree.path.display(), worktree.id, missing).as_str());
+        listed.push(Json::object(vec![
+            ("path", Json::str(worktree.path.display())),
+            ("id", Json::str(&worktree.id)),
+            ("missing", Json::Bool(!linked))
+        ]));
+    }
+    return Ok((output, Json::Array(listed)));
+}
+
+/// Removes every worktree load created. Worktrees with changes are kept (git refuses to remove them),
+/// and stay listed so a later prune can retry. Returns what happened as text and as JSON (the `removed`
+/// paths and the `kept` ones with their `error`, or the `steps_planned` for a dry run)
+pub(crate) fn prune_load_worktrees(git: &dyn GitBackend, dry_run: bool) -> Result<(String, Json), ErrorChain> {
+    let (registry_path, registry) = read_registry(git)?;
+    let worktrees = parse_registry(registry.as_deref().unwrap_or_default());
+    if worktrees.is_empty() {
+        let pruned = Json::object(vec![("removed", Json::Array(Vec::new())), ("kept", Json::Array(Vec::new()))]);
+        return Ok((String::from("No worktrees created by 'cargit load --worktree' to prune"), pruned));
+    }
+    let linked_worktrees = canonical_paths(git.linked_worktrees().on_error("could not list worktrees")?);
+    if dry_run {
+        let mut plan = Plan::new();
+        for worktree in worktrees.iter().filter(|worktree| is_linked(&linked_worktrees, &worktree.path)) {
+            plan.run(git_worktree_remove(&worktree.path));
+        }
+        plan.run(git_worktree_prune());
+        plan.write_file(registry_path, registry, String::new());
+        return Ok((format!("Dry run, nothing was changed!\n{}", plan), Json::object(vec![("steps_planned", Json::strs(plan.summaries()))])));
+    }
+    let mut removed = Vec::new();
+    let mut kept = Vec::new();
+    for worktree in worktrees {
+        if !is_linked(&linked_worktrees, &worktree.path) {
+            removed.push((worktree.path, true));
+            continue;
+        }
+        match git_worktree_remove(&worktree.path).run() {
+            Ok(()) => removed.push((worktree.path, false)),
+            Err(err) => kept.push((worktree, err))
+        }
+    }
+    git_worktree_prune().run().on_error("could not prune the worktree list of git")?;
+    let remaining: Vec<LoadWorktree> = kept.iter().map(|(worktree, _)| worktree.clone()).collect();
+    fs::write(&registry_path, render_registry(&remaining)).on_error(format!("could not update {}", registry_path.display()))?;
+    let mut output = format!("Removed {} worktree(s):", removed.len());
+    for (path, already_gone) in removed.iter() {
+        output.push_str(format!("\n  - {}{}", path.display(), if *already_gone { " (already gone)" } else { "" }).as_str());
+    }
+    if !kept.is_empty() {
+        output.push_str("\nKept, remove them with 'git worktree remove --force <path>' to discard their changes:");
+        for (worktree, err) in kept.iter() {
+            output.push_str(format!("\n  - {}: {}", worktree.path.display(), err).as_str());
+        }
+    }
+    let kept = kept.iter().map(|(worktree, err)| Json::object(vec![("path", Json::str(worktree.path.display())), ("error", Json::str(err))])).collect();
+    return Ok((output, Json::object(vec![("removed", Json::strs(removed.iter().map(|(path, _)| path.display()))), ("kept", Json::Array(kept))])));
+}
+
+fn canonical_paths(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    return paths.into_iter().map(|path| fs::canonicalize(&path).unwrap_or(path)).collect();
+}
+
+fn is_linked(linked_worktrees: &[PathBuf], path: &Path) -> bool {
+    let path = fs::canonicalize(path).unwrap_or(path.to_path_buf());
+    return linked_worktrees.contains(&path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worktree_registry_and_default_path() {
+        let worktrees = vec![
+            LoadWorktree { path: PathBuf::from("/src/crate-v1.4.2"), id: String::from("v1.4.2") },
+            LoadWorktree { path: PathBuf::from("/tmp/old build"), id: String::from("main") },
+        ];
+        assert_eq!(parse_registry(&render_registry(&worktrees)), worktrees);
+        assert_eq!(default_worktree_path(Path::new("/src/crate"), "v1.4.2"), PathBuf::from("/src/crate-v1.4.2"));
+        assert_eq!(default_worktree_path(Path::new("/src/crate"), "feature/x"), PathBuf::from("/src/crate-feature-x"));
+        assert_eq!(default_worktree_path(Path::new("/src/crate"), "0123456789abcdef0123456789abcdef01234567"), PathBuf::from("/src/crate-0123456789ab"));
+    }
+}
@@ -0,0 +1,253 @@
+//! Throwaway git repositories for running the cargit binary end to end: each `TestRepo` is a crate
+//! in a fresh temporary directory, with a bare repo as its `origin` remote and a stub `cargo` first on
+//! `PATH` that records its arguments instead of building or publishing anything. Git runs with the
+//! user and system configs ignored, and the registry index is a local directory, so nothing outside
+//! the temporary directory is read or changed.
+#![allow(clippy::needless_return, dead_code)]
+
+use std::{
+    env, fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    io::Write,
+    process::{self, Command, Output, Stdio},
+    sync::atomic::{AtomicUsize, Ordering}
+};
+
+static NEXT_REPO: AtomicUsize = AtomicUsize::new(0);
+
+/// Records every call on its own line of `$CARGIT_TEST_CARGO_LOG`. Fails calls that start with
+/// `$CARGIT_TEST_CARGO_FAIL`, and reports `cargo semver-checks` as not installed
+const STUB_CARGO: &str = r#"#!/bin/sh
+echo "$*" >> "$CARGIT_TEST_CARGO_LOG"
+case "$*" in
+    semver-checks*) echo "error: no such command: semver-checks" >&2; exit 101 ;;
+esac
+if [ -n "$CARGIT_TEST_CARGO_FAIL" ]; then
+    case "$*" in
+        "$CARGIT_TEST_CARGO_FAIL"*) echo "error: stub cargo was told to fail '$*'" >&2; exit 101 ;;
+    esac
+fi
+exit 0
+"#;
+
+/// The outcome of one cargit run
+pub struct Run {
+    pub code: i32,
+    pub stdout: String,
+    pub stderr: String
+}
+
+impl Run {
+    fn from_output(output: Output) -> Run {
+        return Run {
+            code: output.status.code().unwrap_or(-1),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string()
+        };
+    }
+
+    /// Panics with the captured output unless cargit exited with `code`
+    pub fn expect_code(self, code: i32) -> Run {
+        assert_eq!(self.code, code, "cargit exited with {} instead of {}\nstdout:\n{}\nstderr:\n{}", self.code, code, self.stdout, self.stderr);
+        return self;
+    }
+
+    pub fn expect_success(self) -> Run {
+        return self.expect_code(0);
+    }
+}
+
+/// A crate in its own git repo, removed with everything around it when dropped
+pub struct TestRepo {
+    root: PathBuf,
+    /// The working tree, with `origin` pointing at `remote`
+    pub dir: PathBuf,
+    pub remote: PathBuf,
+    bin: PathBuf,
+    cargo_log: PathBuf,
+    cargo_fail: Option<String>
+}
+
+impl TestRepo {
+    /// A crate named `name` at version `version` with one commit, pushed to `origin/main`
+    pub fn new(name: &str, version: &str) -> TestRepo {
+        let repo = TestRepo::empty();
+        repo.write("Cargo.toml", &format!("[package]\nname = \"{}\"\nversion = \"{}\"\nedition = \"2021\"\n\n[dependencies]\n", name, version));
+        repo.write("src/lib.rs", "pub fn answer() -> u32 {\n    return 42;\n}\n");
+        repo.commit_all("Initial commit");
+        repo.git(&["push", "--quiet", "-u", "origin", "main"]);
+        return repo;
+    }
+
+    /// A crate with the versions 0.1.0, 0.1.1 and 0.2.0 saved by cargit, followed by `untagged` commits, and the
+    /// hashes of all of them, oldest first
+    pub fn released(untagged: usize) -> (TestRepo, Vec<String>) {
+        let repo = TestRepo::new("widget", "0.1.0");
+        let mut commits = vec![repo.head()];
+        repo.git(&["tag", "0.1.0"]);
+        for part in ["patch", "minor"] {
+            repo.cargit(&["save", part, "-m", &format!("Save {}", part)]).expect_success();
+            commits.push(repo.head());
+        }
+        for answer in (43..).take(untagged) {
+            repo.write("src/lib.rs", &format!("pub fn answer() -> u32 {{\n    return {};\n}}\n", answer));
+            commits.push(repo.commit_all(&format!("Answer {}", answer)));
+        }
+        return (repo, commits);
+    }
+
+    /// An empty repo on `main` with its remote, for tests that lay out their own files
+    pub fn empty() -> TestRepo {
+        let root = env::temp_dir().join(format!("cargit-test-{}-{}", process::id(), NEXT_REPO.fetch_add(1, Ordering::SeqCst)));
+        let _ = fs::remove_dir_all(&root);
+        let dir = root.join("crate");
+        let remote = root.join("remote.git");
+        let bin = root.join("bin");
+        for dir in [&dir, &remote, &bin, &root.join("home")] {
+            fs::create_dir_all(dir).expect("could not create the test directories");
+        }
+        let stub_cargo = bin.join("cargo");
+        fs::write(&stub_cargo, STUB_CARGO).expect("could not write the stub cargo");
+        fs::set_permissions(&stub_cargo, fs::Permissions::from_mode(0o755)).expect("could not make the stub cargo executable");
+        let repo = TestRepo { cargo_log: root.join("cargo.log"), root, dir, remote, bin, cargo_fail: None };
+        repo.git_in(&repo.remote, &["init", "--quiet", "--bare", "--initial-branch", "main"]);
+        repo.git(&["init", "--quiet", "--initial-branch", "main"]);
+        repo.git(&["remote", "add", "origin", repo.remote.to_string_lossy().as_ref()]);
+        return repo;
+    }
+
+    /// Makes the stub cargo fail every call whose arguments start with `args`, eg. `"publish"`
+    pub fn fail_cargo(&mut self, args: &str) {
+        self.cargo_fail = Some(args.to_owned());
+    }
+
+    /// A command that only sees the test's own home, config and stub cargo
+    fn command(&self, program: impl AsRef<std::ffi::OsStr>, dir: &Path) -> Command {
+        let mut command = Command::new(program);
+        let path = env::join_paths([self.bin.clone()].into_iter().chain(env::split_paths(&env::var_os("PATH").unwrap_or_default())))
+            .expect("could not build PATH");
+        let home = self.root.join("home");
+        command.current_dir(dir)
+            .env("PATH", path)
+            .env("HOME", &home)
+            .env("XDG_CONFIG_HOME", home.join(".config"))
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env("GIT_CONFIG_GLOBAL", home.join(".gitconfig"))
+            .env("GIT_AUTHOR_NAME", "Test Author")
+            .env("GIT_AUTHOR_EMAIL", "author@example.com")
+            .env("GIT_COMMITTER_NAME", "Test Author")
+            .env("GIT_COMMITTER_EMAIL", "author@example.com")
+            .env("CARGIT_TEST_CARGO_LOG", &self.cargo_log)
+            .env("CARGIT_REGISTRY_INDEX", format!("file://{}", self.root.join("index").display()))
+            .env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE")
+            .stdin(Stdio::null());
+        match &self.cargo_fail {
+            Some(args) => command.env("CARGIT_TEST_CARGO_FAIL", args),
+            None => command.env_remove("CARGIT_TEST_CARGO_FAIL")
+        };
+        return command;
+    }
+
+    /// Runs cargit in the working tree, answering any question with end of input
+    pub fn cargit(&self, args: &[&str]) -> Run {
+        return self.cargit_in(&self.dir, args);
+    }
+
+    pub fn cargit_in(&self, dir: &Path, args: &[&str]) -> Run {
+        let output = self.command(env!("CARGO_BIN_EXE_cargit"), dir).args(args).output().expect("could not run cargit");
+        return Run::from_output(output);
+    }
+
+    /// Runs cargit in the working tree, answering its questions with the lines of `input`
+    pub fn cargit_with_input(&self, args: &[&str], input: &str) -> Run {
+        let mut child = self.command(env!("CARGO_BIN_EXE_cargit"), &self.dir).args(args)
+            .stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped())
+            .spawn().expect("could not run cargit");
+        child.stdin.take().expect("no stdin").write_all(input.as_bytes()).expect("could not answer cargit");
+        return Run::from_output(child.wait_with_output().expect("could not run cargit"));
+    }
+
+    /// Runs git in the working tree and returns its trimmed stdout, panicking if it fails
+    pub fn git(&self, args: &[&str]) -> String {
+        return self.git_in(&self.dir, args);
+    }
+
+    pub fn git_in(&self, dir: &Path, args: &[&str]) -> String {
+        let output = self.command("git", dir).args(args).output().expect("could not run git");
+        assert!(output.status.success(), "git {} failed:\n{}", args.join(" "), String::from_utf8_lossy(&output.stderr));
+        return String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    }
+
+    /// Runs git against the bare remote
+    pub fn remote_git(&self, args: &[&str]) -> String {
+        return self.git_in(&self.remote, args);
+    }
+
+    pub fn write(&self, path: &str, contents: &str) {
+        let path = self.dir.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("could not create the file's directory");
+        }
+        fs::write(path, contents).expect("could not write the file");
+    }
+
+    pub fn read(&self, path: &str) -> String {
+        return fs::read_to_string(self.dir.join(path)).unwrap_or_default();
+    }
+
+    /// Stages everything and commits it, returning the new commit hash
+    pub fn commit_all(&self, message: &str) -> String {
+        self.git(&["add", "--all"]);
+        self.git(&["commit", "--quiet", "-m", message]);
+        return self.head();
+    }
+
+    pub fn head(&self) -> String {
+        return self.git(&["rev-parse", "HEAD"]);
+    }
+
+    /// The checked out branch, or `None` with a detatched HEAD
+    pub fn branch(&self) -> Option<String> {
+        let branch = self.git(&["branch", "--show-current"]);
+        return if branch.is_empty() { None } else { Some(branch) };
+    }
+
+    /// The version field of the crate's Cargo.toml
+    pub fn manifest_version(&self, manifest: &str) -> String {
+        let manifest = self.read(manifest);
+        let line = manifest.lines().find(|line| line.starts_with("version")).expect("no version in the manifest");
+        return line.split('"').nth(1).expect("unquoted version").to_owned();
+    }
+
+    /// Every call the stub cargo received, in order
+    pub fn cargo_calls(&self) -> Vec<String> {
+        return fs::read_to_string(&self.cargo_log).unwrap_or_default().lines().map(|line| line.to_owned()).collect();
+    }
+
+    /// Adds `version` of the crate `name` to the local registry index, as `cargo publish` would
+    pub fn publish_to_index(&self, name: &str, version: &str, yanked: bool) {
+        let path = match name.len() {
+            1 | 2 => format!("{}/{}", name.len(), name),
+            3 => format!("3/{}/{}", &name[..1], name),
+            _ => format!("{}/{}/{}", &name[..2], &name[2..4], name)
+        };
+        let path = self.root.join("index").join(path);
+        fs::create_dir_all(path.parent().expect("index file without a directory")).expect("could not create the index directory");
+        let mut entries = fs::read_to_string(&path).unwrap_or_default();
+        entries.push_str(&format!("{{\"name\":\"{}\",\"vers\":\"{}\",\"deps\":[],\"cksum\":\"00\",\"features\":{{}},\"yanked\":{}}}\n", name, version, yanked));
+        fs::write(path, entries).expect("could not write the index file");
+    }
+
+    /// A path next to the working tree, inside the directory removed on drop
+    pub fn sibling(&self, name: &str) -> PathBuf {
+        return self.root.join(name);
+    }
+}
+
+impl Drop for TestRepo {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
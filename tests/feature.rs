@@ -0,0 +1,104 @@
+#![cfg(unix)]
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::*;
+
+/// A crate on `main` with the feature branch `login` started from it, holding one commit
+fn feature_repo(config: &str) -> TestRepo {
+    let repo = TestRepo::new("widget", "0.1.0");
+    if !config.is_empty() {
+        repo.write(".cargit.toml", config);
+        repo.commit_all("Configure cargit");
+    }
+    repo.cargit(&["start", "login"]).expect_success();
+    assert_eq!(repo.branch().as_deref(), Some("login"));
+    repo.write("src/login.rs", "pub fn login() {}\n");
+    repo.commit_all("Add login");
+    return repo;
+}
+
+#[test]
+fn finish_merges_deletes_and_saves() {
+    let repo = feature_repo("checks = [\"test\"]\n");
+    let feature_commit = repo.head();
+    let run = repo.cargit(&["finish", "--save", "patch", "-m", "Release login"]).expect_success();
+    assert!(run.stdout.contains("Finished login: merged login into main (merge) and deleted login"), "{}", run.stdout);
+    assert_eq!(repo.branch().as_deref(), Some("main"));
+    assert_eq!(repo.git(&["branch", "--list", "login"]), "");
+    repo.git(&["merge-base", "--is-ancestor", &feature_commit, "HEAD"]);
+    assert_eq!(repo.manifest_version("Cargo.toml"), "0.1.1");
+    assert_eq!(repo.git(&["log", "-1", "--format=%s"]), "Release login");
+    assert_eq!(repo.remote_git(&["tag", "--list"]), "0.1.1");
+    // The checks run once on the feature branch, the save after the merge does not repeat them
+    assert_eq!(repo.cargo_calls().iter().filter(|call| call.as_str() == "test --workspace").count(), 1);
+}
+
+#[test]
+fn finish_squashes_or_rebases() {
+    let repo = feature_repo("merge-strategy = \"squash\"\n");
+    let main = repo.git(&["rev-parse", "main"]);
+    repo.write("src/lib.rs", "pub mod login;\n");
+    repo.commit_all("Export login");
+    repo.cargit(&["finish", "-m", "Add login"]).expect_success();
+    assert_eq!(repo.git(&["log", "-1", "--format=%s%n%P"]), format!("Add login\n{}", main));
+    assert_eq!(repo.read("src/lib.rs"), "pub mod login;\n");
+    assert_eq!(repo.git(&["branch", "--list", "login"]), "");
+
+    repo.cargit(&["start", "logout"]).expect_success();
+    repo.write("src/logout.rs", "pub fn logout() {}\n");
+    let feature_commit = repo.commit_all("Add logout");
+    repo.git(&["checkout", "--quiet", "main"]);
+    repo.write("README.md", "# widget\n");
+    let main = repo.commit_all("Add a readme");
+    repo.cargit(&["finish", "logout", "--rebase", "--keep-branch"]).expect_success();
+    assert_eq!(repo.git(&["log", "--format=%s", "-3"]), "Add logout\nAdd a readme\nAdd login");
+    assert_eq!(repo.git(&["rev-parse", "HEAD~1"]), main);
+    assert_ne!(repo.head(), feature_commit);
+    assert_eq!(repo.git(&["rev-parse", "logout"]), repo.head());
+}
+
+#[test]
+fn finish_refuses_or_stops_safely() {
+    let repo = feature_repo("");
+    repo.cargit(&["start", "login"]).expect_code(2);
+    repo.write("src/login.rs", "// work in progress\n");
+    repo.cargit(&["finish"]).expect_code(5);
+    repo.git(&["checkout", "--quiet", "--", "src/login.rs"]);
+
+    let head = repo.head();
+    let run = repo.cargit(&["finish", "--squash", "--dry-run", "--json"]).expect_success();
+    assert!(run.stdout.starts_with(r#"{"ok":true,"command":"finish","dry_run":true,"branch":"login","into":"main","strategy":"squash","checks":[],"deleted":true,"steps_planned":["git checkout main","git merge --squash login","git commit --no-edit","git branch -D login"]"#), "{}", run.stdout);
+    assert_eq!((repo.head(), repo.branch()), (head, Some(String::from("login"))));
+
+    repo.git(&["checkout", "--quiet", "main"]);
+    repo.cargit(&["finish"]).expect_code(2);
+    repo.write("src/login.rs", "pub fn log_in() {}\n");
+    repo.commit_all("Add a different login");
+    let run = repo.cargit(&["finish", "login"]).expect_code(3);
+    assert!(run.stderr.contains("Finish stopped at the failed step"), "{}", run.stderr);
+    assert_eq!(repo.branch().as_deref(), Some("main"));
+    assert!(!repo.git(&["branch", "--list", "login"]).is_empty());
+}
+
+#[test]
+fn finish_runs_the_checks_on_a_named_feature() {
+    let repo = feature_repo("checks = [\"test ! -e src/broken.rs\"]\n");
+    repo.write("src/broken.rs", "");
+    let feature_commit = repo.commit_all("Break the check");
+    repo.git(&["checkout", "--quiet", "main"]);
+    let main = repo.head();
+    let run = repo.cargit(&["finish", "login"]).expect_code(1);
+    assert!(run.stderr.contains("fix the failures on login"), "{}", run.stderr);
+    assert_eq!((repo.head(), repo.branch()), (main, Some(String::from("main"))));
+    assert_eq!(repo.git(&["rev-parse", "login"]), feature_commit);
+
+    repo.git(&["checkout", "--quiet", "login"]);
+    repo.git(&["rm", "--quiet", "src/broken.rs"]);
+    repo.commit_all("Fix the check");
+    repo.git(&["checkout", "--quiet", "main"]);
+    repo.cargit(&["finish", "login"]).expect_success();
+    assert_eq!(repo.branch().as_deref(), Some("main"));
+    assert!(!repo.dir.join("src/broken.rs").exists());
+}
@@ -0,0 +1,110 @@
+#![cfg(unix)]
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::*;
+
+#[test]
+fn load_steps_through_history_and_back_to_the_branch() {
+    let (repo, commits) = TestRepo::released(1);
+    repo.cargit(&["load", "prev"]).expect_success();
+    assert_eq!(repo.head(), commits[2]);
+    assert_eq!(repo.branch(), None);
+    repo.cargit(&["load", "prev", "2"]).expect_success();
+    assert_eq!(repo.head(), commits[0]);
+    repo.cargit(&["load", "next"]).expect_success();
+    assert_eq!(repo.head(), commits[1]);
+    repo.cargit(&["load", "next-release"]).expect_success();
+    assert_eq!(repo.head(), commits[2]);
+    repo.cargit(&["load", "latest"]).expect_success();
+    assert_eq!(repo.head(), commits[3]);
+    assert_eq!(repo.branch().as_deref(), Some("main"));
+}
+
+#[test]
+fn load_finds_versions_and_requirements() {
+    let (repo, commits) = TestRepo::released(1);
+    repo.cargit(&["load", "0.1.1"]).expect_success();
+    assert_eq!(repo.head(), commits[1]);
+    repo.cargit(&["load", "<0.2"]).expect_success();
+    assert_eq!(repo.head(), commits[1]);
+    repo.cargit(&["load", "0.1"]).expect_success();
+    assert_eq!(repo.head(), commits[1]);
+    repo.cargit(&["load", "prev-release"]).expect_success();
+    assert_eq!(repo.head(), commits[0]);
+    let run = repo.cargit(&["load", "^3"]).expect_code(1);
+    assert!(run.stderr.contains("^3"), "{}", run.stderr);
+    repo.cargit(&["load", "no-such-branch"]).expect_code(3);
+}
+
+#[test]
+fn load_dry_run_changes_nothing() {
+    let (repo, commits) = TestRepo::released(1);
+    let run = repo.cargit(&["load", "prev", "--dry-run"]).expect_success();
+    assert!(run.stdout.starts_with("Dry run, nothing was changed!"), "{}", run.stdout);
+    assert!(run.stdout.contains(&commits[2]), "{}", run.stdout);
+    assert_eq!(repo.head(), commits[3]);
+    assert_eq!(repo.branch().as_deref(), Some("main"));
+}
+
+#[test]
+fn load_stashes_uncommitted_changes_and_reapplies_them() {
+    let (repo, commits) = TestRepo::released(1);
+    repo.write("src/lib.rs", "// work in progress\n");
+    let run = repo.cargit(&["load", "prev"]).expect_code(5);
+    assert!(run.stderr.contains("uncommitted changes"), "{}", run.stderr);
+    assert_eq!(repo.head(), commits[3]);
+    assert_eq!(repo.read("src/lib.rs"), "// work in progress\n");
+
+    repo.cargit(&["load", "prev", "--stash"]).expect_success();
+    assert_eq!(repo.head(), commits[2]);
+    assert_eq!(repo.git(&["status", "--porcelain"]), "");
+    assert_eq!(repo.git(&["stash", "list", "--format=%gs"]), "On main: cargit load from main");
+
+    repo.cargit(&["load", "latest", "--stash"]).expect_success();
+    assert_eq!(repo.branch().as_deref(), Some("main"));
+    assert_eq!(repo.read("src/lib.rs"), "// work in progress\n");
+    assert_eq!(repo.git(&["stash", "list"]), "");
+}
+
+#[test]
+fn load_into_a_worktree_and_prune_it() {
+    let (repo, commits) = TestRepo::released(1);
+    let worktree = repo.sibling("widget-0.1.1");
+    repo.cargit(&["load", "0.1.1", &format!("--worktree={}", worktree.display()), "--build"]).expect_success();
+    assert_eq!(repo.head(), commits[3]);
+    assert_eq!(repo.git_in(&worktree, &["rev-parse", "HEAD"]), commits[1]);
+    assert_eq!(repo.cargo_calls().last().cloned(), Some(format!("build --manifest-path {}", worktree.join("Cargo.toml").display())));
+    let run = repo.cargit(&["load", "--list-worktrees"]).expect_success();
+    assert!(run.stdout.contains(worktree.to_string_lossy().as_ref()), "{}", run.stdout);
+
+    let run = repo.cargit(&["load", "--prune"]).expect_success();
+    assert!(run.stdout.contains("Removed 1 worktree(s)"), "{}", run.stdout);
+    assert!(!worktree.exists());
+    assert!(repo.cargit(&["load", "--list-worktrees"]).expect_success().stdout.contains("No worktrees"));
+}
+
+#[test]
+fn load_worktree_does_not_take_the_target_as_its_path() {
+    let (repo, commits) = TestRepo::released(1);
+    repo.cargit(&["load", "--worktree", "0.1.1"]).expect_success();
+    let worktree = repo.dir.with_file_name("crate-0.1.1");
+    assert_eq!(repo.git_in(&worktree, &["rev-parse", "HEAD"]), commits[1]);
+    assert!(!repo.dir.join("0.1.1").exists());
+    assert_eq!(repo.head(), commits[3]);
+    repo.cargit(&["load", "--worktree="]).expect_code(2);
+}
+
+#[test]
+fn load_reports_json() {
+    let (repo, commits) = TestRepo::released(1);
+    let run = repo.cargit(&["load", "prev", "--json"]).expect_success();
+    assert_eq!(run.stdout.trim_end(), format!(
+        r#"{{"ok":true,"command":"load","dry_run":false,"previous_commit":"{0}","ref":"{1}","stashed":null,"reapplied":null,"commit":"{1}","branch":null,"steps_done":["git checkout {1}"],"message":"checked out previous commit ({1}), version 0.2.0 (tag 0.2.0)"}}"#,
+        commits[3], commits[2]
+    ));
+    let run = repo.cargit(&["load", "no-such-branch", "--json"]).expect_code(3);
+    assert!(run.stdout.contains(r#""kind":"git","exit_code":3"#), "{}", run.stdout);
+    assert!(run.stdout.contains("did not match any file(s) known to git"), "{}", run.stdout);
+}
@@ -0,0 +1,210 @@
+#![cfg(unix)]
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::*;
+
+#[test]
+fn save_patch_bumps_commits_tags_and_pushes() {
+    let repo = TestRepo::new("widget", "0.1.0");
+    let run = repo.cargit(&["save", "patch", "-m", "Fix the answer"]).expect_success();
+    assert!(run.stdout.contains("New version: 0.1.1"), "{}", run.stdout);
+    assert_eq!(repo.manifest_version("Cargo.toml"), "0.1.1");
+    assert_eq!(repo.git(&["log", "-1", "--format=%s"]), "Fix the answer");
+    assert_eq!(repo.git(&["status", "--porcelain"]), "");
+    assert_eq!(repo.git(&["rev-parse", "0.1.1^{commit}"]), repo.head());
+    assert_eq!(repo.remote_git(&["rev-parse", "main"]), repo.head());
+    assert_eq!(repo.remote_git(&["tag", "--list"]), "0.1.1");
+    assert!(repo.read("CHANGELOG.md").contains("## [0.1.1] - "));
+    assert_eq!(repo.cargo_calls(), vec!["semver-checks --version", "generate-lockfile"]);
+}
+
+#[test]
+fn save_follows_the_tag_template_and_publishes() {
+    let repo = TestRepo::new("widget", "0.1.0");
+    repo.write(".cargit.toml", "tag-template = \"v{version}\"\nannotate = true\n");
+    repo.commit_all("Configure cargit");
+    repo.cargit(&["save", "minor", "rc", "-publish", "-m", "Release candidate"]).expect_success();
+    assert_eq!(repo.manifest_version("Cargo.toml"), "0.2.0-rc.1");
+    assert_eq!(repo.git(&["cat-file", "-t", "v0.2.0-rc.1"]), "tag");
+    assert_eq!(repo.remote_git(&["tag", "--list"]), "v0.2.0-rc.1");
+    assert_eq!(repo.cargo_calls().last().map(String::as_str), Some("publish"));
+    repo.cargit(&["save", "release", "--no-publish", "-m", "Release"]).expect_success();
+    assert_eq!(repo.manifest_version("Cargo.toml"), "0.2.0");
+    assert_eq!(repo.remote_git(&["tag", "--list"]), "v0.2.0\nv0.2.0-rc.1");
+    assert_eq!(repo.cargo_calls().iter().filter(|call| call.as_str() == "publish").count(), 1);
+}
+
+#[test]
+fn save_auto_picks_the_part_from_conventional_commits() {
+    let repo = TestRepo::new("widget", "1.2.3");
+    repo.git(&["tag", "1.2.3"]);
+    repo.write("src/lib.rs", "pub fn answer() -> u32 {\n    return 42;\n}\n\npub fn question() {}\n");
+    repo.commit_all("feat: ask the question");
+    let run = repo.cargit(&["save", "auto", "-m", "chore: release"]).expect_success();
+    assert!(run.stdout.contains("auto bump"), "{}", run.stdout);
+    assert_eq!(repo.manifest_version("Cargo.toml"), "1.3.0");
+    let changelog = repo.read("CHANGELOG.md");
+    assert!(changelog.contains("### Added") && changelog.contains("ask the question"), "{}", changelog);
+}
+
+#[test]
+fn save_dry_run_changes_nothing() {
+    let repo = TestRepo::new("widget", "0.1.0");
+    let head = repo.head();
+    let run = repo.cargit(&["save", "major", "--dry-run", "-m", "Break everything"]).expect_success();
+    assert!(run.stdout.starts_with("Dry run, nothing was changed!"), "{}", run.stdout);
+    assert!(run.stdout.contains("1.0.0"), "{}", run.stdout);
+    assert!(run.stdout.contains("git push origin main --tags"), "{}", run.stdout);
+    assert_eq!(repo.head(), head);
+    assert_eq!(repo.manifest_version("Cargo.toml"), "0.1.0");
+    assert_eq!(repo.git(&["tag", "--list"]), "");
+    assert!(repo.cargo_calls().iter().all(|call| call.starts_with("semver-checks")));
+}
+
+#[test]
+fn save_rolls_back_when_a_cargo_step_fails() {
+    let mut repo = TestRepo::new("widget", "0.1.0");
+    repo.fail_cargo("publish");
+    let head = repo.head();
+    let run = repo.cargit(&["save", "patch", "-publish", "-m", "Publish me"]).expect_code(4);
+    assert!(run.stderr.contains("stub cargo was told to fail 'publish'"), "{}", run.stderr);
+    // the push already reached the remote, so only that is left behind
+    assert_ne!(repo.remote_git(&["rev-parse", "main"]), head);
+
+    let mut repo = TestRepo::new("widget", "0.1.0");
+    repo.fail_cargo("generate-lockfile");
+    let head = repo.head();
+    repo.cargit(&["save", "patch", "-m", "Never committed"]).expect_code(4);
+    assert_eq!(repo.head(), head);
+    assert_eq!(repo.manifest_version("Cargo.toml"), "0.1.0");
+    assert_eq!(repo.git(&["tag", "--list"]), "");
+    assert_eq!(repo.remote_git(&["tag", "--list"]), "");
+    assert_eq!(repo.git(&["status", "--porcelain"]), "");
+}
+
+#[test]
+fn save_refuses_a_hard_rollback_over_uncommitted_changes() {
+    let mut repo = TestRepo::new("widget", "0.1.0");
+    repo.fail_cargo("publish");
+    repo.write("src/lib.rs", "// work in progress\n");
+    let head = repo.head();
+    let run = repo.cargit(&["save", "patch", "-publish", "--rollback", "hard"]).expect_code(5);
+    assert!(run.stderr.contains("would erase your uncommitted changes"), "{}", run.stderr);
+    assert_eq!(repo.head(), head);
+    assert_eq!(repo.manifest_version("Cargo.toml"), "0.1.0");
+    assert_eq!(repo.read("src/lib.rs"), "// work in progress\n");
+    assert_eq!(repo.cargo_calls(), Vec::<String>::new());
+}
+
+#[test]
+fn save_stops_when_a_check_fails() {
+    let repo = TestRepo::new("widget", "0.1.0");
+    repo.write(".cargit.toml", "checks = [\"test\", \"false\"]\n");
+    repo.commit_all("Add checks");
+    let head = repo.head();
+    let run = repo.cargit(&["save", "patch"]).expect_code(1);
+    assert!(run.stderr.contains("1 of 2 pre-save checks failed"), "{}", run.stderr);
+    assert_eq!(repo.head(), head);
+    assert_eq!(repo.manifest_version("Cargo.toml"), "0.1.0");
+    assert!(repo.cargo_calls().contains(&String::from("test --workspace")));
+    repo.cargit(&["save", "patch", "--skip-checks"]).expect_success();
+    assert_eq!(repo.manifest_version("Cargo.toml"), "0.1.1");
+}
+
+#[test]
+fn save_bumps_changed_workspace_members_and_their_dependents() {
+    let repo = TestRepo::empty();
+    repo.write("Cargo.toml", "[workspace]\nmembers = [\"core\", \"app\"]\nresolver = \"2\"\n");
+    repo.write("core/Cargo.toml", "[package]\nname = \"core\"\nversion = \"0.1.0\"\nedition = \"2021\"\n");
+    repo.write("core/src/lib.rs", "");
+    repo.write("app/Cargo.toml", "[package]\nname = \"app\"\nversion = \"0.3.0\"\nedition = \"2021\"\n\n[dependencies]\ncore = { path = \"../core\", version = \"0.1.0\" }\n");
+    repo.write("app/src/main.rs", "fn main() {}\n");
+    repo.commit_all("Initial commit");
+    repo.git(&["push", "--quiet", "-u", "origin", "main"]);
+    repo.cargit(&["save", "minor", "-p", "core", "-m", "New core"]).expect_success();
+    assert_eq!(repo.manifest_version("core/Cargo.toml"), "0.2.0");
+    assert_eq!(repo.manifest_version("app/Cargo.toml"), "0.3.0");
+    assert!(repo.read("app/Cargo.toml").contains("version = \"0.2.0\""), "{}", repo.read("app/Cargo.toml"));
+    assert_eq!(repo.remote_git(&["tag", "--list"]), "core-v0.2.0");
+}
+
+#[test]
+fn save_lists_the_members_sharing_the_workspace_version() {
+    let repo = TestRepo::empty();
+    repo.write("Cargo.toml", "[workspace]\nmembers = [\"core\", \"app\"]\nresolver = \"2\"\n\n[workspace.package]\nversion = \"0.1.0\"\n");
+    for member in ["core", "app"] {
+        repo.write(&format!("{}/Cargo.toml", member), &format!("[package]\nname = \"{}\"\nversion.workspace = true\nedition = \"2021\"\n", member));
+        repo.write(&format!("{}/src/lib.rs", member), "");
+    }
+    repo.commit_all("Initial commit");
+    let run = repo.cargit(&["save", "patch", "-p", "core", "--dry-run"]).expect_success();
+    assert!(run.stdout.contains("(also saved, as they share the [workspace.package] version of a saved crate: app)"), "{}", run.stdout);
+    let run = repo.cargit(&["save", "patch", "-p", "core", "--dry-run", "--json"]).expect_success();
+    assert!(run.stdout.contains(r#""tag":"app-v0.1.1","bump_reason":null,"selected":false}"#), "{}", run.stdout);
+    assert!(run.stdout.contains(r#""tag":"core-v0.1.1","bump_reason":null,"selected":true}"#), "{}", run.stdout);
+}
+
+#[test]
+fn invalid_arguments_exit_with_a_usage_error() {
+    let repo = TestRepo::new("widget", "0.1.0");
+    let run = repo.cargit(&["save", "patch", "minor"]).expect_code(2);
+    assert!(run.stderr.contains("set more than once"), "{}", run.stderr);
+    repo.cargit(&["bogus"]).expect_code(2);
+}
+
+#[test]
+fn save_reports_json() {
+    let repo = TestRepo::new("widget", "0.1.0");
+    let run = repo.cargit(&["save", "patch", "--json", "-m", "Fix the answer"]).expect_success();
+    let head = repo.head();
+    assert!(run.stdout.starts_with(r#"{"ok":true,"command":"save","dry_run":false,"old_version":"0.1.0","new_version":"0.1.1","tag":"0.1.1","#), "{}", run.stdout);
+    assert!(run.stdout.contains(&format!(r#""commit":"{}","branch":"main","remote":"origin","pushed":true,"published":false,"#, head)), "{}", run.stdout);
+    assert!(run.stdout.contains(r#""steps_skipped":["cargo publish (not requested)"]"#), "{}", run.stdout);
+    assert!(run.stdout.contains(r#""git push origin main --tags"]"#), "{}", run.stdout);
+    assert_eq!(run.stdout.lines().count(), 1, "{}", run.stdout);
+
+    // An option value is never taken for the output format
+    let run = repo.cargit(&["save", "patch", "-m", "--json"]).expect_success();
+    assert!(run.stdout.contains("Saved, Committed, Tagged, Pushed! New version: 0.1.2"), "{}", run.stdout);
+    assert_eq!(repo.git(&["log", "-1", "--format=%s"]), "--json");
+
+    repo.write("Cargo.toml", "[package]\nname = \"widget\"\n");
+    let run = repo.cargit(&["save", "patch", "--format", "json"]).expect_code(1);
+    assert!(run.stdout.starts_with(r#"{"ok":false,"command":"save","error":{"message":"#), "{}", run.stdout);
+    assert!(run.stdout.ends_with("\"kind\":\"error\",\"exit_code\":1}}\n"), "{}", run.stdout);
+}
+
+#[test]
+fn save_from_a_detatched_head_pushes_the_new_branch() {
+    let repo = TestRepo::new("widget", "0.1.0");
+    repo.write("src/lib.rs", "pub fn answer() -> u32 {\n    return 43;\n}\n");
+    repo.commit_all("Change the answer");
+    repo.git(&["checkout", "--quiet", "HEAD~1"]);
+    repo.write("src/lib.rs", "pub fn answer() -> u32 {\n    return 44;\n}\n");
+    let run = repo.cargit_with_input(&["save", "patch", "-m", "Fix the old answer"], "y\nold-answer\n").expect_success();
+    assert!(run.stdout.contains("Created new branch old-answer"), "{}", run.stdout);
+    assert_eq!(repo.branch().as_deref(), Some("old-answer"));
+    assert_eq!(repo.remote_git(&["rev-parse", "old-answer"]), repo.head());
+    assert_eq!(repo.remote_git(&["for-each-ref", "--format=%(refname)", "refs/heads"]), "refs/heads/main\nrefs/heads/old-answer");
+    assert_eq!(repo.remote_git(&["tag", "--list"]), "0.1.1");
+}
+
+#[test]
+fn save_from_a_detatched_head_removes_the_new_branch_on_rollback() {
+    let mut repo = TestRepo::new("widget", "0.1.0");
+    repo.write("src/lib.rs", "pub fn answer() -> u32 {\n    return 43;\n}\n");
+    repo.commit_all("Change the answer");
+    repo.git(&["checkout", "--quiet", "HEAD~1"]);
+    let head = repo.head();
+    repo.write("src/lib.rs", "pub fn answer() -> u32 {\n    return 44;\n}\n");
+    repo.cargit_with_input(&["save", "patch"], "y\n\n").expect_code(5);
+    assert_eq!((repo.head(), repo.branch()), (head.clone(), None));
+
+    repo.fail_cargo("generate-lockfile");
+    repo.cargit_with_input(&["save", "patch"], "y\nold-answer\n").expect_code(4);
+    assert_eq!((repo.head(), repo.branch()), (head, None));
+    assert_eq!(repo.git(&["branch", "--list", "old-answer"]), "");
+    assert_eq!(repo.read("src/lib.rs"), "pub fn answer() -> u32 {\n    return 44;\n}\n");
+}
@@ -0,0 +1,73 @@
+#![cfg(unix)]
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::*;
+
+#[test]
+fn status_shows_versions_tags_and_the_registry() {
+    let repo = TestRepo::new("widget", "0.1.0");
+    let run = repo.cargit(&["status"]).expect_success();
+    assert!(run.stdout.starts_with("widget 0.1.0\n  latest version tag: none yet\n  registry: 0.1.0 is not published\n"), "{}", run.stdout);
+    assert!(run.stdout.contains("Remote: origin/main, 0 ahead, 0 behind"), "{}", run.stdout);
+    assert!(run.stdout.ends_with("Ready to save\n"), "{}", run.stdout);
+
+    repo.cargit(&["save", "patch", "-m", "First release"]).expect_success();
+    repo.publish_to_index("widget", "0.1.1", false);
+    let run = repo.cargit(&["status"]).expect_success();
+    assert!(run.stdout.contains("latest version tag: 0.1.1, at HEAD\n  registry: 0.1.1 is published\n"), "{}", run.stdout);
+    assert!(run.stdout.contains("Not ready to save:\n  - nothing changed since the latest version tag"), "{}", run.stdout);
+
+    repo.write("src/lib.rs", "pub fn answer() -> u32 {\n    return 43;\n}\n");
+    repo.commit_all("Change the answer");
+    repo.write("notes.txt", "todo\n");
+    let run = repo.cargit(&["status", "--offline", "--json"]).expect_success();
+    let head = repo.head();
+    assert!(run.stdout.starts_with(&format!(concat!(
+        r#"{{"ok":true,"command":"status","version":"0.1.1","crates":[{{"name":"widget","version":"0.1.1","latest_tag":"0.1.1","#,
+        r#""latest_tag_version":"0.1.1","commits_since_tag":1,"publish":true,"published":null,"yanked":null}}],"commit":"{}","#,
+        r#""branch":"main","detatched":false,"changed_files":["?? notes.txt"],"#,
+        r#""remote":{{"name":"origin","branch":"main","ahead":1,"behind":0}},"ready":true,"blockers":[],"#
+    ), head)), "{}", run.stdout);
+}
+
+#[test]
+fn status_explains_what_blocks_a_save() {
+    let repo = TestRepo::new("widget", "0.1.0");
+    let first = repo.head();
+    repo.write("src/lib.rs", "");
+    repo.commit_all("Empty the library");
+    repo.git(&["push", "--quiet"]);
+    repo.git(&["reset", "--quiet", "--hard", &first]);
+    let run = repo.cargit(&["status"]).expect_success();
+    assert!(run.stdout.contains("Remote: origin/main, 0 ahead, 1 behind"), "{}", run.stdout);
+    assert!(run.stdout.contains("1 commit(s) behind origin/main"), "{}", run.stdout);
+
+    repo.git(&["checkout", "--quiet", "--detach"]);
+    let run = repo.cargit(&["status"]).expect_success();
+    assert!(run.stdout.contains(&format!("HEAD: detatched at {} (from main)", &first[..12])), "{}", run.stdout);
+    assert!(run.stdout.contains("  - HEAD is detatched"), "{}", run.stdout);
+    repo.cargit(&["status", "--bogus"]).expect_code(2);
+}
+
+#[test]
+fn status_lists_every_workspace_member() {
+    let repo = TestRepo::empty();
+    repo.write("Cargo.toml", "[workspace]\nmembers = [\"core\", \"app\"]\nresolver = \"2\"\n\n[workspace.package]\nversion = \"1.4.0\"\n");
+    repo.write("core/Cargo.toml", "[package]\nname = \"core\"\nversion.workspace = true\nedition = \"2021\"\n");
+    repo.write("core/src/lib.rs", "");
+    repo.write("app/Cargo.toml", "[package]\nname = \"app\"\nversion = \"0.3.0\"\nedition = \"2021\"\npublish = false\n");
+    repo.write("app/src/main.rs", "fn main() {}\n");
+    repo.commit_all("Initial commit");
+    repo.git(&["tag", "core-v1.4.0"]);
+    repo.publish_to_index("core", "1.4.0", true);
+    let run = repo.cargit(&["status"]).expect_success();
+    assert!(run.stdout.starts_with(concat!(
+        "core 1.4.0\n  latest version tag: core-v1.4.0, at HEAD\n  registry: 1.4.0 is published, but yanked\n",
+        "app 0.3.0\n  latest version tag: none yet\n  registry: not published (publish = false)\n"
+    )), "{}", run.stdout);
+    assert!(run.stdout.contains("Remote: origin, main was never pushed to it"), "{}", run.stdout);
+    let run = repo.cargit_in(&repo.dir.join("app"), &["status", "--json"]).expect_success();
+    assert!(run.stdout.starts_with(r#"{"ok":true,"command":"status","version":"0.3.0","crates":[{"name":"app","#), "{}", run.stdout);
+}
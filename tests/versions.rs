@@ -0,0 +1,34 @@
+#![cfg(unix)]
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::*;
+
+#[test]
+fn versions_lists_releases_newest_first() {
+    let (repo, commits) = TestRepo::released(2);
+    let run = repo.cargit(&["versions"]).expect_success();
+    let lines: Vec<&str> = run.stdout.lines().collect();
+    assert_eq!(lines[0], "Releases on main, newest first:");
+    for (line, (tag, commit, since)) in lines[1..4].iter().zip([("0.2.0", &commits[2], "(1 commit(s) since 0.1.1)"), ("0.1.1", &commits[1], "(1 commit(s) since 0.1.0)"), ("0.1.0", &commits[0], "(1 commit(s), first release)")]) {
+        assert!(line.trim_start().starts_with(tag) && line.contains(&commit[..12]) && line.contains("Test Author") && line.ends_with(since), "{}", run.stdout);
+    }
+    assert_eq!(&lines[4..], ["2 commit(s) on main after 0.2.0", "HEAD is 2 commit(s) after 0.2.0, at the tip of main"]);
+    assert_eq!(repo.cargit(&["history"]).expect_success().stdout, run.stdout);
+    repo.cargit(&["versions", "--bogus"]).expect_code(2);
+}
+
+#[test]
+fn versions_graph_places_head_among_releases() {
+    let (repo, commits) = TestRepo::released(2);
+    repo.cargit(&["load", "0.1.1"]).expect_success();
+    let run = repo.cargit(&["versions", "--graph"]).expect_success();
+    let markers: Vec<&str> = run.stdout.lines().map(|line| if line.starts_with('|') { line } else { line.split("  ").next().unwrap_or_default() }).collect();
+    assert_eq!(markers, ["* main", "|   2 commit(s)", "o 0.2.0", "|   1 commit(s)", "@ 0.1.1 (HEAD)", "|   1 commit(s)", "o 0.1.0", "HEAD is at 0.1.1, 3 commit(s) behind the tip of main"], "{}", run.stdout);
+    assert!(run.stdout.contains(&commits[4][..12]), "{}", run.stdout);
+
+    let run = repo.cargit(&["versions", "--json"]).expect_success();
+    assert!(run.stdout.starts_with(&format!(r#"{{"ok":true,"command":"versions","branch":"main","tip":"{}","head":"{}","head_in_branch":true,"commits_after_latest":2,"head_release":"0.1.1","commits_after_head_release":0,"releases":[{{"tag":"0.2.0","version":"0.2.0","commit":"{}""#, commits[4], commits[1], commits[2])), "{}", run.stdout);
+    assert!(run.stdout.contains(r#""previous":null,"commits_since_previous":1}]"#), "{}", run.stdout);
+}